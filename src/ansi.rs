@@ -1,10 +1,12 @@
 #[cfg(feature = "alloc")]
-use alloc::{borrow::Cow, string::String};
+use alloc::{borrow::Cow, string::String, vec::Vec};
 use core::{
-    fmt::Display,
+    fmt::{Display, Write},
     iter::{FusedIterator, Peekable},
-    str::CharIndices,
+    str::{CharIndices, Split},
 };
+#[cfg(feature = "alloc")]
+use core::{mem, str::from_utf8};
 
 #[derive(Debug, Clone, Copy, Default)]
 enum State {
@@ -21,6 +23,20 @@ enum State {
     S9,
     S10,
     S11,
+    /// Right after an 8-bit CSI, which unlike `ESC` cannot start a string.
+    Csi,
+    /// Inside the payload of an OSC string (`ESC ]`).
+    Osc,
+    /// Saw an `ESC` inside an OSC string, expecting the `\` of the ST.
+    OscEsc,
+    /// Right after `ESC P`, which on its own is also a complete escape.
+    Dcs,
+    /// Inside the payload of a DCS, SOS, PM or APC string.
+    CtrlStr,
+    /// Saw an `ESC` inside a control string, expecting the `\` of the ST.
+    CtrlStrEsc,
+    /// The control string was terminated.
+    StrEnd,
     Trap,
 }
 
@@ -28,7 +44,34 @@ impl State {
     fn is_final(&self) -> bool {
         #[allow(clippy::match_like_matches_macro)]
         match self {
-            Self::S3 | Self::S5 | Self::S6 | Self::S7 | Self::S8 | Self::S9 | Self::S11 => true,
+            Self::S3
+            | Self::S5
+            | Self::S6
+            | Self::S7
+            | Self::S8
+            | Self::S9
+            | Self::S11
+            | Self::Dcs
+            | Self::StrEnd => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` for final states that no char can extend.
+    #[cfg(feature = "alloc")]
+    fn is_terminal(&self) -> bool {
+        #[allow(clippy::match_like_matches_macro)]
+        match self {
+            Self::S3 | Self::S9 | Self::S11 | Self::StrEnd => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` inside of a DCS, SOS, PM or APC string.
+    fn is_control_string(&self) -> bool {
+        #[allow(clippy::match_like_matches_macro)]
+        match self {
+            Self::Dcs | Self::CtrlStr | Self::CtrlStrEsc => true,
             _ => false,
         }
     }
@@ -42,11 +85,53 @@ impl State {
     }
 
     fn transition(&mut self, c: char) {
-        *self = match c {
-            '\u{1b}' | '\u{9b}' => match self {
+        *self = match self {
+            Self::Osc => match c {
+                // Terminated by either BEL or ST (in its 7-bit and 8-bit forms)
+                '\u{07}' | '\u{9c}' => Self::StrEnd,
+                '\u{1b}' => Self::OscEsc,
+                // Any other C0 control or an 8-bit CSI aborts the string
+                '\u{00}'..='\u{1f}' | '\u{9b}' => Self::Trap,
+                _ => Self::Osc,
+            },
+            Self::OscEsc => match c {
+                '\\' => Self::StrEnd,
+                _ => Self::Trap,
+            },
+            Self::Dcs | Self::CtrlStr => match c {
+                '\u{9c}' => Self::StrEnd,
+                '\u{1b}' => Self::CtrlStrEsc,
+                // CAN and SUB cancel the string, an 8-bit CSI aborts it
+                '\u{18}' | '\u{1a}' | '\u{9b}' => Self::Trap,
+                _ => Self::CtrlStr,
+            },
+            Self::CtrlStrEsc => match c {
+                '\\' => Self::StrEnd,
+                // tmux passthrough doubles every escape inside the DCS payload
+                '\u{1b}' => Self::CtrlStr,
+                _ => Self::Trap,
+            },
+            Self::Csi => match c {
+                // `CSI P` is DCH, strings are only introduced by `ESC`
+                'P' => Self::S11,
+                ']' | 'X' | '^' | '_' => Self::Trap,
+                _ => Self::S1.transition_escape(c),
+            },
+            Self::StrEnd | Self::Trap => Self::Trap,
+            _ => self.transition_escape(c),
+        };
+    }
+
+    fn transition_escape(&self, c: char) -> Self {
+        match c {
+            '\u{1b}' => match self {
                 Self::Start => Self::S1,
                 _ => Self::Trap,
             },
+            '\u{9b}' => match self {
+                Self::Start => Self::Csi,
+                _ => Self::Trap,
+            },
             '(' | ')' => match self {
                 Self::S1 => Self::S2,
                 Self::S2 | Self::S4 => Self::S4,
@@ -58,6 +143,21 @@ impl State {
                 _ => Self::Trap,
             },
 
+            ']' => match self {
+                Self::S1 => Self::Osc,
+                _ => Self::Trap,
+            },
+            'X' | '^' | '_' => match self {
+                Self::S1 => Self::CtrlStr,
+                _ => Self::Trap,
+            },
+            'P' => match self {
+                Self::S1 => Self::Dcs,
+                Self::S2 | Self::S4 | Self::S5 | Self::S6 | Self::S7 | Self::S8 | Self::S10 => {
+                    Self::S11
+                }
+                _ => Self::Trap,
+            },
             '[' | '#' | '?' => match self {
                 Self::S1 | Self::S2 | Self::S4 => Self::S4,
                 _ => Self::Trap,
@@ -82,7 +182,7 @@ impl State {
                 Self::S10 => Self::S5,
                 _ => Self::Trap,
             },
-            'A'..='P' | 'R' | 'Z' | 'c' | 'f'..='n' | 'q' | 'r' | 'y' | '=' | '>' | '<' => {
+            'A'..='O' | 'R' | 'Z' | 'c' | 'f'..='n' | 'q' | 'r' | 'y' | '=' | '>' | '<' => {
                 match self {
                     Self::S1
                     | Self::S2
@@ -96,20 +196,25 @@ impl State {
                 }
             }
             _ => Self::Trap,
-        };
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Matches<'a> {
     s: &'a str,
     it: Peekable<CharIndices<'a>>,
+    failed_string_end: usize,
 }
 
 impl<'a> Matches<'a> {
     fn new(s: &'a str) -> Self {
         let it = s.char_indices().peekable();
-        Self { s, it }
+        Self {
+            s,
+            it,
+            failed_string_end: 0,
+        }
     }
 }
 
@@ -131,46 +236,80 @@ impl<'a> Iterator for Matches<'a> {
     type Item = Match<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        find_ansi_code_exclusive(&mut self.it).map(|(start, end)| Match {
-            text: self.s,
-            start,
-            end,
+        find_ansi_code_exclusive(&mut self.it, &mut self.failed_string_end).map(|(start, end)| {
+            Match {
+                text: self.s,
+                start,
+                end,
+            }
         })
     }
 }
 
 impl FusedIterator for Matches<'_> {}
 
-fn find_ansi_code_exclusive(it: &mut Peekable<CharIndices>) -> Option<(usize, usize)> {
+/// Finds the next ansi code.
+///
+/// `failed_string_end` remembers where the last unterminated control string ended.  A control
+/// string that starts inside of it (after a doubled `ESC`) runs into the same end, so it is not
+/// scanned again.  This keeps the scan linear.
+fn find_ansi_code_exclusive(
+    it: &mut Peekable<CharIndices>,
+    failed_string_end: &mut usize,
+) -> Option<(usize, usize)> {
     'outer: loop {
         if let (start, '\u{1b}') | (start, '\u{9b}') = it.peek()? {
             let start = *start;
+            let restart = it.clone();
+            // The first char after `start` that can start another match
+            let mut resume = None;
             let mut state = State::default();
             let mut maybe_end = None;
 
             loop {
-                let item = it.peek();
+                let item = it.peek().copied();
+
+                let in_string = state.is_control_string();
 
                 if let Some((idx, c)) = item {
-                    state.transition(*c);
+                    if idx > start && resume.is_none() && matches!(c, '\u{1b}' | '\u{9b}') {
+                        resume = Some(it.clone());
+                    }
+                    let entered_string = !in_string;
+                    state.transition(c);
 
                     if state.is_final() {
-                        maybe_end = Some(*idx);
+                        maybe_end = Some(idx + c.len_utf8());
+                    }
+                    if entered_string && state.is_control_string() && idx < *failed_string_end {
+                        state = State::Trap;
                     }
                 }
 
                 // The match is greedy so run till we hit the trap state no matter what. A valid
                 // match is just one that was final at some point
                 if state.is_trapped() || item.is_none() {
+                    if in_string {
+                        *failed_string_end = item.map_or(usize::MAX, |(idx, _)| idx);
+                    }
                     match maybe_end {
                         Some(end) => {
-                            // All possible final characters are a single byte so it's safe to make
-                            // the end exclusive by just adding one
-                            return Some((start, end + 1));
+                            // A fallback match (like a bare `ESC P`) may have been followed by
+                            // entry chars, so resume scanning right after the match
+                            if it.peek().map_or(true, |&(idx, _)| idx != end) {
+                                *it = restart;
+                                while it.next_if(|&(idx, _)| idx < end).is_some() {}
+                            }
+                            return Some((start, end));
+                        }
+                        // Nothing before the first entry char seen since `start` can start a
+                        // match, so resume there or at the char that trapped
+                        None => {
+                            if let Some(resume) = resume {
+                                *it = resume;
+                            }
+                            continue 'outer;
                         }
-                        // The character we are peeking right now might be the start of a match so
-                        // we want to continue the loop without popping off that char
-                        None => continue 'outer,
                     }
                 }
 
@@ -186,11 +325,16 @@ fn find_ansi_code_exclusive(it: &mut Peekable<CharIndices>) -> Option<(usize, us
 #[cfg(feature = "alloc")]
 pub fn strip_ansi_codes(s: &str) -> Cow<'_, str> {
     let mut char_it = s.char_indices().peekable();
-    match find_ansi_code_exclusive(&mut char_it) {
+    match find_ansi_code_exclusive(&mut char_it, &mut 0) {
         Some(_) => {
-            let stripped: String = AnsiCodeIterator::new(s)
-                .filter_map(|(text, is_ansi)| if is_ansi { None } else { Some(text) })
-                .collect();
+            let mut stripped = String::with_capacity(s.len());
+            for token in AnsiTokens::new(s) {
+                match token {
+                    AnsiToken::Text(text) => stripped.push_str(text),
+                    AnsiToken::Control(c) => stripped.push(c),
+                    _ => {}
+                }
+            }
             Cow::Owned(stripped)
         }
         None => Cow::Borrowed(s),
@@ -225,6 +369,7 @@ impl Display for WithoutAnsi<'_> {
 /// It yields tuples in the form `(s, is_ansi)` where `s` is a slice of
 /// the original string and `is_ansi` indicates if the slice contains
 /// ansi codes or string values.
+#[derive(Debug, Clone)]
 pub struct AnsiCodeIterator<'a> {
     s: &'a str,
     pending_item: Option<(&'a str, bool)>,
@@ -287,6 +432,632 @@ impl<'a> Iterator for AnsiCodeIterator<'a> {
 
 impl FusedIterator for AnsiCodeIterator<'_> {}
 
+/// The kind of a [`ControlString`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlStringKind {
+    /// Device control string (`ESC P`).
+    Dcs,
+    /// Start of string (`ESC X`).
+    Sos,
+    /// Privacy message (`ESC ^`).
+    Pm,
+    /// Application program command (`ESC _`).
+    Apc,
+}
+
+/// A plain escape sequence like `ESC 7` or `ESC ( B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscSequence<'a> {
+    raw: &'a str,
+    /// The intermediate characters between `ESC` and the final character.
+    pub intermediates: &'a str,
+    /// The final character of the sequence.
+    pub final_char: char,
+}
+
+impl<'a> EscSequence<'a> {
+    /// Returns the sequence as it appeared in the input.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+}
+
+/// A control sequence introduced by `ESC [` (or the 8-bit `CSI`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsiSequence<'a> {
+    raw: &'a str,
+    /// The parameters of the sequence.
+    pub params: CsiParams<'a>,
+    /// The intermediate characters between the parameters and the final character.
+    pub intermediates: &'a str,
+    /// The final character which selects the control function (eg: `m` for SGR).
+    pub final_char: char,
+}
+
+impl<'a> CsiSequence<'a> {
+    /// Returns the sequence as it appeared in the input.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+}
+
+/// The parameters of a [`CsiSequence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsiParams<'a>(&'a str);
+
+impl<'a> CsiParams<'a> {
+    /// Returns the raw parameter string including a private marker.
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// Returns the private marker (one of `<`, `=`, `>` or `?`) if there is one.
+    ///
+    /// For instance `ESC [ ? 25 h` has the private marker `?`.
+    pub fn private_marker(&self) -> Option<char> {
+        self.0.chars().next().filter(|c| matches!(c, '<'..='?'))
+    }
+
+    /// Iterates over the `;` separated numeric parameters.
+    ///
+    /// Empty parameters (which select the default value of the control
+    /// function) and parameters that are not a valid number yield `None`.
+    pub fn iter(&self) -> CsiParamsIter<'a> {
+        let s = match self.private_marker() {
+            Some(marker) => &self.0[marker.len_utf8()..],
+            None => self.0,
+        };
+        CsiParamsIter((!s.is_empty()).then(|| s.split(';')))
+    }
+}
+
+impl<'a> IntoIterator for CsiParams<'a> {
+    type Item = Option<u16>;
+    type IntoIter = CsiParamsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the numeric parameters of a [`CsiSequence`].
+#[derive(Debug, Clone)]
+pub struct CsiParamsIter<'a>(Option<Split<'a, char>>);
+
+impl Iterator for CsiParamsIter<'_> {
+    type Item = Option<u16>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.as_mut()?.next().map(|param| param.parse().ok())
+    }
+}
+
+impl FusedIterator for CsiParamsIter<'_> {}
+
+/// An operating system command introduced by `ESC ]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OscSequence<'a> {
+    raw: &'a str,
+    /// The command number (eg: `8` for hyperlinks or `2` for the window title).
+    pub command: &'a str,
+    /// Everything after the first `;`, without the terminator.
+    pub payload: &'a str,
+}
+
+impl<'a> OscSequence<'a> {
+    /// Returns the sequence as it appeared in the input.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+}
+
+/// A DCS, SOS, PM or APC control string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlString<'a> {
+    raw: &'a str,
+    /// The kind of the control string.
+    pub kind: ControlStringKind,
+    /// The contents of the string, without the introducer and terminator.
+    pub payload: &'a str,
+}
+
+impl<'a> ControlString<'a> {
+    /// Returns the control string as it appeared in the input.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+}
+
+/// A token yielded by [`AnsiTokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiToken<'a> {
+    /// A run of text without any control characters.
+    Text(&'a str),
+    /// A single C0 control character such as `\n`, `\r` or `\t`.
+    Control(char),
+    /// A plain escape sequence.
+    Esc(EscSequence<'a>),
+    /// A control sequence.
+    Csi(CsiSequence<'a>),
+    /// An operating system command.
+    Osc(OscSequence<'a>),
+    /// A DCS, SOS, PM or APC control string.
+    ControlString(ControlString<'a>),
+}
+
+impl<'a> AnsiToken<'a> {
+    /// Returns `true` if this token is an escape sequence.
+    pub fn is_ansi(&self) -> bool {
+        !matches!(self, AnsiToken::Text(_) | AnsiToken::Control(_))
+    }
+
+    pub(crate) fn from_escape(raw: &'a str) -> AnsiToken<'a> {
+        let mut chars = raw.chars();
+        let introducer = chars.next();
+        let body = chars.as_str();
+        let mut body_chars = body.chars();
+        let kind = body_chars.next();
+        let rest = body_chars.as_str();
+
+        match kind {
+            Some('[') => AnsiToken::Csi(CsiSequence::parse(raw, rest)),
+            Some(']') => {
+                let inner = strip_string_terminator(rest);
+                let (command, payload) = inner.split_once(';').unwrap_or((inner, ""));
+                AnsiToken::Osc(OscSequence {
+                    raw,
+                    command,
+                    payload,
+                })
+            }
+            // A bare `ESC P` is a complete escape on its own
+            Some(c @ ('P' | 'X' | '^' | '_')) if !rest.is_empty() => {
+                AnsiToken::ControlString(ControlString {
+                    raw,
+                    kind: match c {
+                        'P' => ControlStringKind::Dcs,
+                        'X' => ControlStringKind::Sos,
+                        '^' => ControlStringKind::Pm,
+                        _ => ControlStringKind::Apc,
+                    },
+                    payload: strip_string_terminator(rest),
+                })
+            }
+            _ if introducer == Some('\u{9b}') => AnsiToken::Csi(CsiSequence::parse(raw, body)),
+            _ => {
+                let (intermediates, final_char) = split_final_char(body);
+                AnsiToken::Esc(EscSequence {
+                    raw,
+                    intermediates,
+                    final_char,
+                })
+            }
+        }
+    }
+}
+
+impl Display for AnsiToken<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AnsiToken::Text(s) => f.write_str(s),
+            AnsiToken::Control(c) => f.write_char(*c),
+            AnsiToken::Esc(esc) => f.write_str(esc.raw),
+            AnsiToken::Csi(csi) => f.write_str(csi.raw),
+            AnsiToken::Osc(osc) => f.write_str(osc.raw),
+            AnsiToken::ControlString(string) => f.write_str(string.raw),
+        }
+    }
+}
+
+impl<'a> CsiSequence<'a> {
+    fn parse(raw: &'a str, body: &'a str) -> Self {
+        let (inner, final_char) = split_final_char(body);
+        let params_len = inner
+            .find(|c| !matches!(c, '\u{30}'..='\u{3f}'))
+            .unwrap_or(inner.len());
+        CsiSequence {
+            raw,
+            params: CsiParams(&inner[..params_len]),
+            intermediates: &inner[params_len..],
+            final_char,
+        }
+    }
+}
+
+fn split_final_char(s: &str) -> (&str, char) {
+    let mut chars = s.chars();
+    let final_char = chars.next_back().unwrap_or_default();
+    (chars.as_str(), final_char)
+}
+
+fn strip_string_terminator(s: &str) -> &str {
+    s.strip_suffix("\u{1b}\\")
+        .or_else(|| s.strip_suffix(['\u{07}', '\u{9c}']))
+        .unwrap_or(s)
+}
+
+/// An iterator over the typed tokens of a string.
+///
+/// Unlike [`AnsiCodeIterator`] this splits the string into text, control
+/// characters and the different kinds of escape sequences, with their
+/// parameters already parsed.  It does not allocate.
+///
+/// ```
+/// # use console::{AnsiToken, AnsiTokens};
+/// let mut tokens = AnsiTokens::new("\x1b[1;31mError\x1b[0m\n");
+/// let Some(AnsiToken::Csi(csi)) = tokens.next() else { panic!() };
+/// assert_eq!(csi.final_char, 'm');
+/// assert_eq!(csi.params.iter().collect::<Vec<_>>(), [Some(1), Some(31)]);
+/// assert_eq!(tokens.next(), Some(AnsiToken::Text("Error")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnsiTokens<'a> {
+    s: &'a str,
+    text: &'a str,
+    pending: Option<&'a str>,
+    last_idx: usize,
+    iter: Matches<'a>,
+}
+
+impl<'a> AnsiTokens<'a> {
+    /// Creates a new token iterator.
+    pub fn new(s: &'a str) -> AnsiTokens<'a> {
+        AnsiTokens {
+            s,
+            text: "",
+            pending: None,
+            last_idx: 0,
+            iter: Matches::new(s),
+        }
+    }
+}
+
+impl<'a> Iterator for AnsiTokens<'a> {
+    type Item = AnsiToken<'a>;
+
+    fn next(&mut self) -> Option<AnsiToken<'a>> {
+        loop {
+            let text = self.text;
+            if !text.is_empty() {
+                return Some(match text.find(|c| matches!(c, '\u{00}'..='\u{1f}')) {
+                    Some(0) => {
+                        self.text = &text[1..];
+                        AnsiToken::Control(text.as_bytes()[0] as char)
+                    }
+                    Some(idx) => {
+                        self.text = &text[idx..];
+                        AnsiToken::Text(&text[..idx])
+                    }
+                    None => {
+                        self.text = "";
+                        AnsiToken::Text(text)
+                    }
+                });
+            }
+
+            if let Some(raw) = self.pending.take() {
+                return Some(AnsiToken::from_escape(raw));
+            }
+
+            match self.iter.next() {
+                Some(m) => {
+                    self.text = &self.s[self.last_idx..m.start];
+                    self.pending = Some(m.as_str());
+                    self.last_idx = m.end;
+                }
+                None if self.last_idx < self.s.len() => {
+                    self.text = &self.s[self.last_idx..];
+                    self.last_idx = self.s.len();
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+impl FusedIterator for AnsiTokens<'_> {}
+
+/// An event emitted by [`AnsiStreamParser`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiEvent<'a> {
+    /// A run of text.
+    Text(&'a str),
+    /// A complete ansi escape code.
+    Escape(&'a str),
+}
+
+/// An incremental ansi code parser for byte chunks.
+///
+/// This finds the same ansi codes as [`AnsiCodeIterator`] but accepts the
+/// input in arbitrary chunks (for instance as returned by `read()` on a
+/// pipe).  Escape codes and UTF-8 characters that are split across chunks
+/// are held back until they are complete.  Invalid UTF-8 is replaced with
+/// `U+FFFD`.  An escape code that is still unfinished after a few KiB is
+/// given up on and emitted the same way as by [`AnsiStreamParser::finish`].
+///
+/// ```
+/// # use console::{AnsiEvent, AnsiStreamParser};
+/// let mut parser = AnsiStreamParser::new();
+/// let mut text = String::new();
+/// for chunk in [&b"Hello \x1b[3"[..], b"1mWorld\x1b", b"[0m!"] {
+///     parser.feed(chunk, |event| {
+///         if let AnsiEvent::Text(s) = event {
+///             text.push_str(s);
+///         }
+///     });
+/// }
+/// parser.finish(|_| {});
+/// assert_eq!(text, "Hello World!");
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct AnsiStreamParser {
+    state: State,
+    pending: String,
+    maybe_end: Option<usize>,
+    /// Index of the first entry char in `pending` after its start.
+    resume: Option<usize>,
+    /// Position of `pending` in the stream, used for `failed_string_end`.
+    offset: u64,
+    /// Same as in `find_ansi_code_exclusive`, but as a stream position.
+    failed_string_end: u64,
+    utf8: [u8; 4],
+    utf8_len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl AnsiStreamParser {
+    /// Creates a new stream parser.
+    pub fn new() -> AnsiStreamParser {
+        AnsiStreamParser::default()
+    }
+
+    /// Returns `true` if a partial escape code or character is held back.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty() || self.utf8_len > 0
+    }
+
+    /// Feeds the next chunk of input to the parser.
+    ///
+    /// Events for everything that can be decided on so far are passed to `f`.
+    pub fn feed<F: FnMut(AnsiEvent<'_>)>(&mut self, mut chunk: &[u8], mut f: F) {
+        if self.utf8_len > 0 {
+            let width = utf8_char_width(self.utf8[0]);
+            while self.utf8_len < width {
+                match chunk.split_first() {
+                    Some((&b, rest)) if b & 0xc0 == 0x80 => {
+                        self.utf8[self.utf8_len] = b;
+                        self.utf8_len += 1;
+                        chunk = rest;
+                    }
+                    Some(_) => break,
+                    None => return,
+                }
+            }
+            let len = mem::take(&mut self.utf8_len);
+            let utf8 = self.utf8;
+            self.process(from_utf8(&utf8[..len]).unwrap_or("\u{fffd}"), &mut f);
+        }
+
+        loop {
+            match from_utf8(chunk) {
+                Ok(s) => return self.process(s, &mut f),
+                Err(err) => {
+                    let (valid, rest) = chunk.split_at(err.valid_up_to());
+                    // SAFETY: `valid_up_to` is the length of the valid prefix
+                    self.process(unsafe { core::str::from_utf8_unchecked(valid) }, &mut f);
+                    match err.error_len() {
+                        Some(len) => {
+                            self.process("\u{fffd}", &mut f);
+                            chunk = &rest[len..];
+                        }
+                        None => {
+                            self.utf8[..rest.len()].copy_from_slice(rest);
+                            self.utf8_len = rest.len();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Signals the end of the input.
+    ///
+    /// Anything that is still held back is resolved and passed to `f`, after
+    /// which the parser can be reused for a new stream.
+    pub fn finish<F: FnMut(AnsiEvent<'_>)>(&mut self, mut f: F) {
+        if mem::take(&mut self.utf8_len) > 0 {
+            self.process("\u{fffd}", &mut f);
+        }
+        while !self.pending.is_empty() {
+            if self.state.is_control_string() {
+                self.failed_string_end = u64::MAX;
+            }
+            let replay = self.resolve(&mut f);
+            for c in replay.chars() {
+                self.push_escape_char(c, &mut f);
+            }
+        }
+        self.offset = 0;
+        self.failed_string_end = 0;
+    }
+
+    fn process<F: FnMut(AnsiEvent<'_>)>(&mut self, s: &str, f: &mut F) {
+        let mut text_start = 0;
+        for (idx, c) in s.char_indices() {
+            if self.pending.is_empty() && c != '\u{1b}' && c != '\u{9b}' {
+                continue;
+            }
+            if text_start < idx {
+                self.offset += (idx - text_start) as u64;
+                f(AnsiEvent::Text(&s[text_start..idx]));
+            }
+            text_start = idx + c.len_utf8();
+            self.push_escape_char(c, f);
+        }
+        if text_start < s.len() {
+            self.offset += (s.len() - text_start) as u64;
+            f(AnsiEvent::Text(&s[text_start..]));
+        }
+    }
+
+    /// Advances the state machine with a char that is part of (or starts) an escape code.
+    fn push_escape_char<F: FnMut(AnsiEvent<'_>)>(&mut self, c: char, f: &mut F) {
+        // Chars that need to be scanned again after a failed or finished match, in reverse
+        let mut replay = Vec::new();
+        let mut next = Some(c);
+
+        while let Some(c) = next.take().or_else(|| replay.pop()) {
+            let is_entry_char = c == '\u{1b}' || c == '\u{9b}';
+            if self.pending.is_empty() && !is_entry_char {
+                self.offset += c.len_utf8() as u64;
+                f(AnsiEvent::Text(c.encode_utf8(&mut [0; 4])));
+                continue;
+            }
+
+            let pos = self.offset + self.pending.len() as u64;
+            if is_entry_char && !self.pending.is_empty() && self.resume.is_none() {
+                self.resume = Some(self.pending.len());
+            }
+            let in_string = self.state.is_control_string();
+            self.pending.push(c);
+            self.state.transition(c);
+            if self.state.is_final() {
+                self.maybe_end = Some(self.pending.len());
+            }
+            if !in_string && self.state.is_control_string() && pos < self.failed_string_end {
+                self.state = State::Trap;
+            }
+            if self.state.is_trapped() && in_string {
+                self.failed_string_end = pos;
+            }
+            if self.state.is_trapped() || self.state.is_terminal() {
+                replay.extend(self.resolve(f).chars().rev());
+            } else if self.pending.len() >= MAX_PENDING_LEN {
+                // Resolve like `finish` so that a lost terminator doesn't stall the output
+                if self.state.is_control_string() {
+                    self.failed_string_end = self.offset + self.pending.len() as u64;
+                }
+                replay.extend(self.resolve(f).chars().rev());
+            }
+        }
+    }
+
+    /// Emits the pending match (or the text before the next possible match if there is none)
+    /// and returns the chars that have to be scanned again.
+    fn resolve<F: FnMut(AnsiEvent<'_>)>(&mut self, f: &mut F) -> String {
+        let resume = match self.maybe_end.take() {
+            Some(end) => {
+                f(AnsiEvent::Escape(&self.pending[..end]));
+                end
+            }
+            None => {
+                // The char that trapped the state machine can start a new match
+                let fallback = if self.state.is_trapped() {
+                    self.pending.char_indices().last().map_or(0, |(idx, _)| idx)
+                } else {
+                    self.pending.len()
+                };
+                let len = self.resume.unwrap_or(fallback);
+                f(AnsiEvent::Text(&self.pending[..len]));
+                len
+            }
+        };
+        self.state = State::default();
+        self.resume = None;
+        self.offset += resume as u64;
+        let replay = self.pending[resume..].into();
+        self.pending.clear();
+        replay
+    }
+}
+
+/// The longest escape code the stream parser holds back before giving up on it.
+#[cfg(feature = "alloc")]
+const MAX_PENDING_LEN: usize = 4096;
+
+#[cfg(feature = "alloc")]
+fn utf8_char_width(b: u8) -> usize {
+    match b {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+/// A writer that strips ansi codes on the fly.
+///
+/// Everything written to this writer is forwarded to the inner writer
+/// without any ansi codes.  Since an escape code might continue in the
+/// next write, [`StripAnsiWriter::finish`] needs to be called at the end
+/// to write out incomplete trailing escape codes.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StripAnsiWriter<W: std::io::Write> {
+    inner: W,
+    parser: AnsiStreamParser,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> StripAnsiWriter<W> {
+    /// Creates a new writer wrapping `inner`.
+    pub fn new(inner: W) -> StripAnsiWriter<W> {
+        StripAnsiWriter {
+            inner,
+            parser: AnsiStreamParser::new(),
+        }
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Writes out anything that is still held back and returns the inner writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        let inner = &mut self.inner;
+        let mut rv = Ok(());
+        self.parser
+            .finish(|event| write_text(inner, event, &mut rv));
+        rv?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+fn write_text<W: std::io::Write>(
+    inner: &mut W,
+    event: AnsiEvent<'_>,
+    rv: &mut std::io::Result<()>,
+) {
+    if let (AnsiEvent::Text(s), Ok(())) = (event, &rv) {
+        *rv = inner.write_all(s.as_bytes());
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> std::io::Write for StripAnsiWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let inner = &mut self.inner;
+        let mut rv = Ok(());
+        self.parser
+            .feed(buf, |event| write_text(inner, event, &mut rv));
+        rv.map(|_| buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,12 +1068,13 @@ mod tests {
     use std::sync::OnceLock;
 
     // The manual dfa `State` is a handwritten translation from the previously used regex. That
-    // regex is kept here and used to ensure that the new matches are the same as the old
+    // regex is kept here (extended with OSC and the other control strings) and used to ensure
+    // that the new matches are the same as the old
     fn strip_ansi_re() -> &'static Regex {
         static RE: OnceLock<Regex> = OnceLock::new();
 
         RE.get_or_init(|| Regex::new(
-            r"[\x1b\x9b]([()][012AB]|[\[()#;?]*(?:[0-9]{1,4}(?:;[0-9]{0,4})*)?[0-9A-PRZcf-nqry=><])",
+            r"\x1b\][^\x00-\x1f\x9b\x9c]*(?:\x07|\x9c|\x1b\\)|\x1b[PX^_](?:[^\x1b\x9b\x9c\x18\x1a]|\x1b\x1b)*(?:\x9c|\x1b\\)|[\x1b\x9b](?:[()][012AB]|[\[()#;?]*(?:[0-9]{1,4}(?:;[0-9]{0,4})*)?[0-9A-PRZcf-nqry=><])",
         ).unwrap())
     }
 
@@ -319,6 +1091,15 @@ mod tests {
             let new_matches: Vec<_> = Matches::new(&s).collect();
             assert_eq!(old_matches, new_matches);
         }
+
+        #[test]
+        fn dfa_matches_old_regex_on_control_strings(
+            s in r"([\x1b\x9b][\]PX^_\[]?[\x07\x18\x1b\x9c\\;0-9a-z ]{0,8}){0,5}"
+        ) {
+            let old_matches: Vec<_> = strip_ansi_re().find_iter(&s).collect();
+            let new_matches: Vec<_> = Matches::new(&s).collect();
+            assert_eq!(old_matches, new_matches);
+        }
     }
 
     #[test]
@@ -326,7 +1107,9 @@ mod tests {
         // To make sure the test runs in a reasonable time this is a slimmed down list of
         // characters to reduce the groups that are only used with each other along with one
         // arbitrarily chosen character not used in the regex (' ')
-        const POSSIBLE_BYTES: &[u8] = &[b' ', 0x1b, 0x9b, b'(', b'0', b'[', b';', b'3', b'C'];
+        const POSSIBLE_BYTES: &[u8] = &[
+            b' ', 0x1b, 0x9b, b'(', b'0', b'[', b';', b'3', b'C', b']', 0x07, b'\\',
+        ];
 
         fn check_all_strings_of_len(len: usize) {
             _check_all_strings_of_len(len, &mut Vec::with_capacity(len));
@@ -405,6 +1188,338 @@ mod tests {
         assert_eq!(&[s], matches.as_slice());
     }
 
+    #[test]
+    fn osc_sequences() {
+        for s in [
+            "\x1b]0;title\x07",
+            "\x1b]2;wintermute@ai:~/console\x1b\\",
+            "\x1b]8;;https://example.com/\u{9c}",
+            "\x1b]7;file://host/tmp/ünïcödé\x07",
+        ] {
+            let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+            assert_eq!(&[s], matches.as_slice());
+        }
+    }
+
+    #[test]
+    fn unterminated_osc() {
+        // Control characters abort the string and a nested escape is still found
+        let s = "\x1b]0;title\n\x1b]8;;url\x1b[31m";
+        let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+        assert_eq!(&["\x1b[31m"], matches.as_slice());
+    }
+
+    #[test]
+    fn osc_needs_7bit_introducer() {
+        // An 8-bit CSI never starts an OSC and aborts one it appears in
+        let s = "\u{9b}]0;a\x07\x1b]0;b\u{9b}1m\x07";
+        let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+        assert_eq!(&["\u{9b}1m"], matches.as_slice());
+    }
+
+    #[test]
+    fn unterminated_osc_scan_is_linear() {
+        // Every failed attempt used to be rescanned from the next char, which took
+        // seconds for inputs like these
+        let start = std::time::Instant::now();
+        for s in ["\u{9b}]a", "\x1b]a", "\x1b]a\u{9b}"] {
+            let s = s.repeat(32000);
+            assert_eq!(strip_ansi_codes(&s), s);
+        }
+        assert!(start.elapsed() < core::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn control_strings() {
+        for s in [
+            "\x1bPq#0;2;0;0;0#1;2;100;100;0#1~~@@vv@@~~@@~~$-\x1b\\",
+            "\x1bPtmux;\x1b\x1b]8;;https://example.com\x07\x1b\\",
+            "\x1b_Gf=100,a=T;iVBORw0KGgo=\x1b\\",
+            "\x1b^private message\u{9c}",
+            "\x1bXstart of string\nwith newline\x1b\\",
+        ] {
+            let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+            assert_eq!(&[s], matches.as_slice());
+        }
+    }
+
+    #[test]
+    fn unterminated_dcs() {
+        // An unterminated DCS falls back to the bare `ESC P` escape
+        let s = "\x1bPabc\x1b[1m\x1b_def\x18";
+        let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+        assert_eq!(&["\x1bP", "\x1b[1m"], matches.as_slice());
+    }
+
+    #[test]
+    fn control_strings_need_7bit_introducer() {
+        // `CSI P` is DCH and the other string introducers are no CSI finals
+        let s = "\u{9b}Pa\u{9b}Xb\x1b\\\x1b_c\u{9b}2K\x1b\\";
+        let matches: Vec<_> = Matches::new(s).map(|m| m.as_str()).collect();
+        assert_eq!(&["\u{9b}P", "\u{9b}2K"], matches.as_slice());
+    }
+
+    #[test]
+    fn unterminated_control_string_scan_is_linear() {
+        // Every failed attempt used to be rescanned from the next char, which took
+        // seconds for inputs like these
+        let start = std::time::Instant::now();
+        for s in ["\u{9b}Pa", "\x1bXa", "\x1bPa", "\x1b\x1bPa", "\x1b\x1bXa"] {
+            let s = s.repeat(32000);
+            let stripped = strip_ansi_codes(&s);
+            assert_eq!(
+                stripped.len() + Matches::new(&s).map(|m| m.as_str().len()).sum::<usize>(),
+                s.len()
+            );
+            assert_eq!(stream_events(&[s.as_bytes()]), iter_events(&s));
+        }
+        assert!(start.elapsed() < core::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_ansi_iter_control_strings() {
+        let s = "a\x1bPq#0~-\x1b\\b\x1b_Ga=d\x1b\\c";
+        let items: Vec<_> = AnsiCodeIterator::new(s).collect();
+        assert_eq!(
+            items,
+            [
+                ("a", false),
+                ("\x1bPq#0~-\x1b\\", true),
+                ("b", false),
+                ("\x1b_Ga=d\x1b\\", true),
+                ("c", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ansi_iter_osc_hyperlink() {
+        let s = "see \x1b]8;;https://example.com\x07docs\x1b]8;;\x07!";
+        let mut iter = AnsiCodeIterator::new(s);
+        assert_eq!(iter.next(), Some(("see ", false)));
+        assert_eq!(iter.next(), Some(("\x1b]8;;https://example.com\x07", true)));
+        assert_eq!(iter.next(), Some(("docs", false)));
+        assert_eq!(iter.next(), Some(("\x1b]8;;\x07", true)));
+        assert_eq!(iter.next(), Some(("!", false)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(strip_ansi_codes(s), "see docs!");
+    }
+
+    #[test]
+    fn test_tokens() {
+        let s = "\x1b[?25l\x1b(Bok\r\n\x1b]8;;https://example.com\x1b\\x\x1bPq#0\x1b\\\u{9b}2K";
+        let tokens: Vec<_> = AnsiTokens::new(s).collect();
+        assert_eq!(tokens.len(), 9);
+
+        let AnsiToken::Csi(csi) = tokens[0] else {
+            panic!("expected csi, got {:?}", tokens[0]);
+        };
+        assert_eq!(csi.as_str(), "\x1b[?25l");
+        assert_eq!(csi.params.private_marker(), Some('?'));
+        assert_eq!(csi.params.iter().collect::<Vec<_>>(), [Some(25)]);
+        assert_eq!(csi.intermediates, "");
+        assert_eq!(csi.final_char, 'l');
+
+        let AnsiToken::Esc(esc) = tokens[1] else {
+            panic!("expected esc, got {:?}", tokens[1]);
+        };
+        assert_eq!((esc.intermediates, esc.final_char), ("(", 'B'));
+
+        assert_eq!(tokens[2], AnsiToken::Text("ok"));
+        assert_eq!(tokens[3], AnsiToken::Control('\r'));
+        assert_eq!(tokens[4], AnsiToken::Control('\n'));
+
+        let AnsiToken::Osc(osc) = tokens[5] else {
+            panic!("expected osc, got {:?}", tokens[5]);
+        };
+        assert_eq!((osc.command, osc.payload), ("8", ";https://example.com"));
+
+        assert_eq!(tokens[6], AnsiToken::Text("x"));
+
+        let AnsiToken::ControlString(dcs) = tokens[7] else {
+            panic!("expected control string, got {:?}", tokens[7]);
+        };
+        assert_eq!((dcs.kind, dcs.payload), (ControlStringKind::Dcs, "q#0"));
+
+        let AnsiToken::Csi(csi) = tokens[8] else {
+            panic!("expected csi, got {:?}", tokens[8]);
+        };
+        assert_eq!(csi.params.iter().collect::<Vec<_>>(), [Some(2)]);
+        assert_eq!(csi.final_char, 'K');
+    }
+
+    #[test]
+    fn test_csi_params() {
+        let params = |s| match AnsiTokens::new(s).next() {
+            Some(AnsiToken::Csi(csi)) => csi.params.iter().collect::<Vec<_>>(),
+            other => panic!("expected csi, got {other:?}"),
+        };
+        assert_eq!(params("\x1b[m"), []);
+        assert_eq!(params("\x1b[;1m"), [None, Some(1)]);
+        assert_eq!(params("\x1b[38;5;208m"), [Some(38), Some(5), Some(208)]);
+    }
+
+    #[test]
+    fn tokens_match_ansi_code_iterator() {
+        let s = std::fs::read_to_string(
+            std::path::Path::new("tests")
+                .join("data")
+                .join("sample_zellij_session.log"),
+        )
+        .unwrap();
+
+        let mut rebuilt = String::new();
+        let mut escapes = vec![];
+        for token in AnsiTokens::new(&s) {
+            let start = rebuilt.len();
+            write!(rebuilt, "{token}").unwrap();
+            if token.is_ansi() {
+                escapes.push(&s[start..rebuilt.len()]);
+            }
+        }
+        assert_eq!(rebuilt, s);
+
+        let old_escapes: Vec<_> = AnsiCodeIterator::new(&s)
+            .filter_map(|(s, is_ansi)| is_ansi.then_some(s))
+            .collect();
+        assert_eq!(escapes, old_escapes);
+    }
+
+    fn stream_events(chunks: &[&[u8]]) -> Vec<(String, bool)> {
+        let mut events: Vec<(String, bool)> = vec![];
+        let mut push = |event: AnsiEvent<'_>| {
+            let (s, is_ansi) = match event {
+                AnsiEvent::Text(s) => (s, false),
+                AnsiEvent::Escape(s) => (s, true),
+            };
+            // Merge adjacent text so the result is independent of the chunking
+            match events.last_mut() {
+                Some((last, false)) if !is_ansi => last.push_str(s),
+                _ => events.push((s.to_string(), is_ansi)),
+            }
+        };
+        let mut parser = AnsiStreamParser::new();
+        for chunk in chunks {
+            parser.feed(chunk, &mut push);
+        }
+        parser.finish(&mut push);
+        assert!(!parser.has_pending());
+        events
+    }
+
+    fn iter_events(s: &str) -> Vec<(String, bool)> {
+        AnsiCodeIterator::new(s)
+            .map(|(s, is_ansi)| (s.to_string(), is_ansi))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn stream_parser_matches_iterator(
+            s in r"([\x1b\x9b][\]P_\[(]?[\x07\x1b\x9c\\;0-9a-zé ]{0,8}){0,5}",
+            splits in proptest::collection::vec(any::<usize>(), 0..6),
+        ) {
+            let mut splits: Vec<_> = splits.into_iter().map(|idx| idx % (s.len() + 1)).collect();
+            splits.sort_unstable();
+            let mut chunks = vec![];
+            let mut last = 0;
+            for idx in splits.into_iter().chain([s.len()]) {
+                chunks.push(&s.as_bytes()[last..idx]);
+                last = idx;
+            }
+            assert_eq!(stream_events(&chunks), iter_events(&s));
+        }
+    }
+
+    #[test]
+    fn stream_parser_complex_data() {
+        let s = std::fs::read_to_string(
+            std::path::Path::new("tests")
+                .join("data")
+                .join("sample_zellij_session.log"),
+        )
+        .unwrap();
+
+        for chunk_size in [1, 2, 7, 64, 4096] {
+            let chunks: Vec<_> = s.as_bytes().chunks(chunk_size).collect();
+            assert_eq!(stream_events(&chunks), iter_events(&s));
+        }
+    }
+
+    #[test]
+    fn stream_parser_utf8() {
+        let s = "\x1b]0;tïtle\x07日本\x1b[1m語";
+        let chunks: Vec<_> = s.as_bytes().chunks(1).collect();
+        assert_eq!(stream_events(&chunks), iter_events(s));
+
+        // Invalid and truncated sequences are replaced
+        assert_eq!(
+            stream_events(&[b"a\xff", b"\xe6\x97", b"b\xe6"]),
+            [("a\u{fffd}\u{fffd}b\u{fffd}".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn stream_parser_emits_finished_escapes_immediately() {
+        let mut parser = AnsiStreamParser::new();
+        for s in ["\x1b[31m", "\x1b]0;title\x07", "\x1b(B", "\x1b_x\x1b\\"] {
+            let mut events = vec![];
+            parser.feed(s.as_bytes(), |event| {
+                events.push(event == AnsiEvent::Escape(s))
+            });
+            assert_eq!(events, [true], "{s:?}");
+            assert!(!parser.has_pending());
+        }
+    }
+
+    #[test]
+    fn stream_parser_caps_pending() {
+        let mut parser = AnsiStreamParser::new();
+        let mut text = String::new();
+        let mut push = |event: AnsiEvent<'_>| {
+            if let AnsiEvent::Text(s) = event {
+                text.push_str(s);
+            }
+        };
+        parser.feed(b"x\x1bP", &mut push);
+        for _ in 0..100_000 {
+            parser.feed(b"a", &mut push);
+        }
+        assert!(text.len() > 90_000);
+        assert!(text.starts_with("xaaa"));
+
+        // Output resumes after the cap and later escapes are found again
+        text.clear();
+        let mut escapes = vec![];
+        parser.feed(b"b\x1b[1mc", |event| match event {
+            AnsiEvent::Text(s) => text.push_str(s),
+            AnsiEvent::Escape(s) => escapes.push(s.to_string()),
+        });
+        assert!(text.ends_with("bc"));
+        assert_eq!(escapes, ["\x1b[1m"]);
+    }
+
+    #[test]
+    fn test_strip_ansi_writer() {
+        use std::io::Write;
+
+        let s = format!(
+            "a\x1b]8;;url\x07{}\x1b]8;;\x07",
+            crate::style("link").red().force_styling(true)
+        );
+        let mut writer = StripAnsiWriter::new(Vec::new());
+        for b in s.as_bytes() {
+            writer.write_all(&[*b]).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), b"alink");
+
+        // A trailing incomplete escape is written out on finish
+        let mut writer = StripAnsiWriter::new(Vec::new());
+        writer.write_all(b"x\x1b[").unwrap();
+        assert_eq!(writer.get_ref(), b"x");
+        assert_eq!(writer.finish().unwrap(), b"x\x1b[");
+    }
+
     #[test]
     fn test_without_ansi() {
         let str_with_ansi = "\x1b[1;97;41mError\x1b[0m";
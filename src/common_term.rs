@@ -70,3 +70,33 @@ pub(crate) fn show_cursor(out: &Term) -> io::Result<()> {
 pub(crate) fn hide_cursor(out: &Term) -> io::Result<()> {
     out.write_str("\x1b[?25l")
 }
+
+#[inline]
+pub(crate) fn enable_mouse_capture(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?1000h\x1b[?1002h\x1b[?1006h")
+}
+
+#[inline]
+pub(crate) fn disable_mouse_capture(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?1006l\x1b[?1002l\x1b[?1000l")
+}
+
+#[inline]
+pub(crate) fn enable_bracketed_paste(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?2004h")
+}
+
+#[inline]
+pub(crate) fn disable_bracketed_paste(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?2004l")
+}
+
+#[inline]
+pub(crate) fn enable_focus_reporting(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?1004h")
+}
+
+#[inline]
+pub(crate) fn disable_focus_reporting(out: &Term) -> io::Result<()> {
+    out.write_str("\x1b[?1004l")
+}
@@ -0,0 +1,415 @@
+use alloc::{borrow::Cow, string::String};
+use core::fmt::Write;
+
+use crate::ansi::{AnsiCodeIterator, AnsiToken};
+use crate::utils::{Attribute, Color, Style};
+
+/// Options for converting ansi colored text to HTML with
+/// [`ansi_to_html_with`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HtmlOptions<'a> {
+    class_prefix: Option<&'a str>,
+    link_schemes: &'a [&'a str],
+    foreground: Color,
+    background: Color,
+}
+
+impl<'a> HtmlOptions<'a> {
+    /// Creates options that render styles as inline `style` attributes.
+    ///
+    /// Only `http`, `https` and `mailto` links are rendered as links and
+    /// the page is assumed to show white on black text.
+    pub const fn new() -> Self {
+        Self {
+            class_prefix: None,
+            link_schemes: &["http", "https", "mailto"],
+            foreground: Color::White,
+            background: Color::Black,
+        }
+    }
+
+    /// Renders styles as CSS classes with the given prefix.
+    ///
+    /// Colors of the 16 color palette are named after the color
+    /// (`fg-red`, `bg-bright-blue`), other 256 colors use their number
+    /// (`fg-208`) and attributes use their name (`bold`, `underline`,
+    /// `strikethrough`, ...).  True colors have no class and are always
+    /// rendered as inline styles.
+    pub const fn css_classes(mut self, prefix: &'a str) -> Self {
+        self.class_prefix = Some(prefix);
+        self
+    }
+
+    /// Sets the URI schemes of hyperlinks that are turned into links.
+    ///
+    /// Schemes are compared case-insensitively.  Hyperlinks with any other
+    /// scheme or without a scheme are rendered as plain text, so that for
+    /// instance `javascript:` links in untrusted output cannot end up in
+    /// the page.
+    pub const fn link_schemes(mut self, schemes: &'a [&'a str]) -> Self {
+        self.link_schemes = schemes;
+        self
+    }
+
+    /// Sets the default foreground color of the page.
+    ///
+    /// With inline styles, reverse video uses it as the background color of
+    /// text without an explicit foreground color.
+    pub const fn foreground(mut self, color: Color) -> Self {
+        self.foreground = color;
+        self
+    }
+
+    /// Sets the default background color of the page.
+    ///
+    /// With inline styles, reverse video uses it as the text color of text
+    /// without an explicit background color.
+    pub const fn background(mut self, color: Color) -> Self {
+        self.background = color;
+        self
+    }
+
+    fn allows_link(&self, uri: &str) -> bool {
+        uri.split_once(':').is_some_and(|(scheme, _)| {
+            self.link_schemes
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+        })
+    }
+}
+
+impl Default for HtmlOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Converts ansi colored text to HTML.
+///
+/// This is a shortcut for [`ansi_to_html_with`] with inline styles.
+pub fn ansi_to_html(s: &str) -> String {
+    ansi_to_html_with(s, &HtmlOptions::new())
+}
+
+/// Converts ansi colored text to HTML according to the given options.
+///
+/// SGR sequences are turned into `<span>` elements that are closed again
+/// as soon as the style changes, OSC 8 hyperlinks with an allowed scheme
+/// (see [`HtmlOptions::link_schemes`]) are turned into `<a>` elements and
+/// the text is escaped.  Colors are taken from the default
+/// xterm palette.  Other escape codes and control characters except for
+/// newlines and tabs are removed.  The result is meant to be placed in a
+/// `<pre>` element.
+///
+/// ```
+/// # use console::{ansi_to_html, ansi_to_html_with, HtmlOptions};
+/// let s = "\x1b[1;31mError:\x1b[0m a < b";
+/// assert_eq!(
+///     ansi_to_html(s),
+///     "<span style=\"color:#cd0000;font-weight:bold\">Error:</span> a &lt; b"
+/// );
+/// assert_eq!(
+///     ansi_to_html_with(s, &HtmlOptions::new().css_classes("ansi-")),
+///     "<span class=\"ansi-fg-red ansi-bold\">Error:</span> a &lt; b"
+/// );
+/// ```
+pub fn ansi_to_html_with(s: &str, options: &HtmlOptions<'_>) -> String {
+    let mut rv = String::new();
+    let mut style = Style::new();
+    let mut link: Option<&str> = None;
+    let mut open_style = Style::new();
+    let mut open_link: Option<&str> = None;
+
+    for (item, is_ansi) in AnsiCodeIterator::new(s) {
+        if is_ansi {
+            match AnsiToken::from_escape(item) {
+                AnsiToken::Osc(osc) if osc.command == "8" => {
+                    let uri = osc.payload.split_once(';').map_or("", |(_, uri)| uri);
+                    link = Some(uri).filter(|uri| options.allows_link(uri));
+                }
+                _ => style = style.apply_escape(item),
+            }
+            continue;
+        }
+
+        if link != open_link {
+            close_span(&mut rv, &mut open_style);
+            if open_link.is_some() {
+                rv.push_str("</a>");
+            }
+            if let Some(uri) = link {
+                rv.push_str("<a href=\"");
+                push_escaped(&mut rv, uri);
+                rv.push_str("\">");
+            }
+            open_link = link;
+        }
+        if style != open_style {
+            close_span(&mut rv, &mut open_style);
+            if style != Style::new() {
+                push_span(&mut rv, &style, options);
+                open_style = style.clone();
+            }
+        }
+        push_escaped(&mut rv, item);
+    }
+
+    close_span(&mut rv, &mut open_style);
+    if open_link.is_some() {
+        rv.push_str("</a>");
+    }
+    rv
+}
+
+fn close_span(rv: &mut String, open_style: &mut Style) {
+    if *open_style != Style::new() {
+        rv.push_str("</span>");
+        *open_style = Style::new();
+    }
+}
+
+fn push_span(rv: &mut String, style: &Style, options: &HtmlOptions<'_>) {
+    let (mut fg, mut bg) = (style.fg_color(), style.bg_color());
+    if style.has_attr(Attribute::Reverse) {
+        (fg, bg) = if options.class_prefix.is_some() {
+            // The reverse class takes care of the default colors
+            (bg, fg)
+        } else {
+            (
+                Some(bg.unwrap_or(options.background)),
+                Some(fg.unwrap_or(options.foreground)),
+            )
+        };
+    }
+
+    let mut classes = String::new();
+    let mut css = String::new();
+    if let Some(prefix) = options.class_prefix {
+        for (kind, color) in [("fg", fg), ("bg", bg)] {
+            if let Some(Color::Color256(n)) = color {
+                let _ = write!(classes, " {prefix}{kind}-{}", color_class(n));
+            } else {
+                push_color_css(&mut css, kind, color);
+            }
+        }
+        for (attr, name) in ATTRIBUTE_NAMES {
+            if style.has_attr(attr) {
+                let _ = write!(classes, " {prefix}{name}");
+            }
+        }
+    } else {
+        push_color_css(&mut css, "fg", fg);
+        push_color_css(&mut css, "bg", bg);
+        if style.has_attr(Attribute::Bold) {
+            css.push_str(";font-weight:bold");
+        }
+        if style.has_attr(Attribute::Dim) {
+            css.push_str(";opacity:0.5");
+        }
+        if style.has_attr(Attribute::Italic) {
+            css.push_str(";font-style:italic");
+        }
+        let decorations = [
+            (Attribute::Underlined, " underline"),
+            (Attribute::StrikeThrough, " line-through"),
+            (Attribute::Blink, " blink"),
+            (Attribute::BlinkFast, " blink"),
+        ];
+        let mut decoration = String::new();
+        for (attr, value) in decorations {
+            if style.has_attr(attr) && !decoration.ends_with(value) {
+                decoration.push_str(value);
+            }
+        }
+        if !decoration.is_empty() {
+            let _ = write!(css, ";text-decoration:{}", &decoration[1..]);
+        }
+        if style.has_attr(Attribute::Hidden) {
+            css.push_str(";visibility:hidden");
+        }
+    }
+
+    rv.push_str("<span");
+    if !classes.is_empty() {
+        let _ = write!(rv, " class=\"{}\"", &classes[1..]);
+    }
+    if !css.is_empty() {
+        let _ = write!(rv, " style=\"{}\"", &css[1..]);
+    }
+    rv.push('>');
+}
+
+const ATTRIBUTE_NAMES: [(Attribute, &str); 9] = [
+    (Attribute::Bold, "bold"),
+    (Attribute::Dim, "dim"),
+    (Attribute::Italic, "italic"),
+    (Attribute::Underlined, "underline"),
+    (Attribute::Blink, "blink"),
+    (Attribute::BlinkFast, "blink-fast"),
+    (Attribute::Reverse, "reverse"),
+    (Attribute::Hidden, "hidden"),
+    (Attribute::StrikeThrough, "strikethrough"),
+];
+
+fn push_color_css(css: &mut String, kind: &str, color: Option<Color>) {
+    if let Some(color) = color {
+        let (r, g, b) = color.rgb();
+        let property = if kind == "fg" {
+            "color"
+        } else {
+            "background-color"
+        };
+        let _ = write!(css, ";{property}:#{r:02x}{g:02x}{b:02x}");
+    }
+}
+
+fn color_class(n: u8) -> Cow<'static, str> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    match n {
+        0..=7 => Cow::Borrowed(NAMES[n as usize]),
+        8..=15 => Cow::Owned(format!("bright-{}", NAMES[n as usize - 8])),
+        _ => Cow::Owned(n.to_string()),
+    }
+}
+
+/// Appends text to HTML or XML output with special characters escaped.
+pub(crate) fn push_escaped(rv: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => rv.push_str("&amp;"),
+            '<' => rv.push_str("&lt;"),
+            '>' => rv.push_str("&gt;"),
+            '"' => rv.push_str("&quot;"),
+            '\'' => rv.push_str("&#39;"),
+            '\n' | '\t' => rv.push(c),
+            c if c.is_control() => {}
+            c => rv.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::style;
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(ansi_to_html("foo bar"), "foo bar");
+        assert_eq!(
+            ansi_to_html("<a href='x'>&</a>\r\n\tb\x07"),
+            "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;\n\tb"
+        );
+    }
+
+    #[test]
+    fn test_inline_styles() {
+        let s = format!(
+            "{} {} {}",
+            style("a")
+                .red()
+                .bright()
+                .on_color256(17)
+                .force_styling(true),
+            style("b")
+                .true_color(1, 2, 3)
+                .italic()
+                .underlined()
+                .strikethrough()
+                .force_styling(true),
+            style("c").color256(232).dim().hidden().force_styling(true),
+        );
+        assert_eq!(
+            ansi_to_html(&s),
+            "<span style=\"color:#ff0000;background-color:#00005f\">a</span> \
+             <span style=\"color:#010203;font-style:italic;text-decoration:underline line-through\">b</span> \
+             <span style=\"color:#080808;opacity:0.5;visibility:hidden\">c</span>"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[7;32mx\x1b[27my"),
+            "<span style=\"color:#000000;background-color:#00cd00\">x</span>\
+             <span style=\"color:#00cd00\">y</span>"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[7mx"),
+            "<span style=\"color:#000000;background-color:#e5e5e5\">x</span>"
+        );
+        let options = HtmlOptions::new()
+            .foreground(Color::Color256(16))
+            .background(Color::TrueColor(255, 255, 255));
+        assert_eq!(
+            ansi_to_html_with("\x1b[7mx", &options),
+            "<span style=\"color:#ffffff;background-color:#000000\">x</span>"
+        );
+    }
+
+    #[test]
+    fn test_css_classes() {
+        let options = HtmlOptions::new().css_classes("c-");
+        assert_eq!(
+            ansi_to_html_with(
+                "\x1b[1;94;48;5;200ma\x1b[22;48;2;0;0;255mb\x1b[mc",
+                &options
+            ),
+            "<span class=\"c-fg-bright-blue c-bg-200 c-bold\">a</span>\
+             <span class=\"c-fg-bright-blue\" style=\"background-color:#0000ff\">b</span>c"
+        );
+        assert_eq!(
+            ansi_to_html_with("\x1b[7;4mx", &options),
+            "<span class=\"c-underline c-reverse\">x</span>"
+        );
+    }
+
+    #[test]
+    fn test_resets() {
+        assert_eq!(
+            ansi_to_html("\x1b[31ma\x1b[0mb\x1b[32mc\x1b[39md\x1b[1m\x1b[22me"),
+            "<span style=\"color:#cd0000\">a</span>b\
+             <span style=\"color:#00cd00\">c</span>de"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[31ma\x1b[2Kb\x1b[0m"),
+            "<span style=\"color:#cd0000\">ab</span>"
+        );
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        let s = "see \x1b]8;;https://example.com/?a=1&b=\"2\"\x1b\\\x1b[1mthe\x1b[0m docs\x1b]8;;\x1b\\!";
+        assert_eq!(
+            ansi_to_html(s),
+            "see <a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">\
+             <span style=\"font-weight:bold\">the</span> docs</a>!"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[31m\x1b]8;id=1;http://x\x07a\x1b]8;;mailto:y\x07b\x1b]8;;\x07c"),
+            "<a href=\"http://x\"><span style=\"color:#cd0000\">a</span></a>\
+             <a href=\"mailto:y\"><span style=\"color:#cd0000\">b</span></a>\
+             <span style=\"color:#cd0000\">c</span>"
+        );
+    }
+
+    #[test]
+    fn test_hyperlink_schemes() {
+        for uri in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,x",
+            "x",
+        ] {
+            let s = format!("\x1b]8;;{uri}\x1b\\a\x1b]8;;\x1b\\b");
+            assert_eq!(ansi_to_html(&s), "ab");
+        }
+        assert_eq!(
+            ansi_to_html("\x1b]8;;HTTPS://x\x07a\x1b]8;;\x07"),
+            "<a href=\"HTTPS://x\">a</a>"
+        );
+        let options = HtmlOptions::new().link_schemes(&["file"]);
+        assert_eq!(
+            ansi_to_html_with("\x1b]8;;file:///x\x07a\x1b]8;;https://x\x07b", &options),
+            "<a href=\"file:///x\">a</a>b"
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use alloc::vec::Vec;
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+    mem,
+    ops::{BitOr, BitOrAssign},
+};
 
 /// Key mapping
 ///
@@ -29,4 +33,1024 @@ pub enum Key {
     PageDown,
     Char(char),
     CtrlC,
+    /// A function key, `F(1)` is F1.
+    F(u8),
+    /// A mouse report, see [`Term::capture_mouse`](crate::Term::capture_mouse).
+    Mouse(MouseEvent),
+    /// Pasted text, see
+    /// [`Term::enable_bracketed_paste`](crate::Term::enable_bracketed_paste).
+    ///
+    /// Very long pastes are split up into several keys.
+    Paste(String),
+    /// The terminal gained focus, see
+    /// [`Term::enable_focus_reporting`](crate::Term::enable_focus_reporting).
+    FocusGained,
+    /// The terminal lost focus.
+    FocusLost,
+}
+
+/// The mouse button of a [`MouseEvent`].
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+    /// No button is held, the mouse was only moved.
+    None,
+    Unknown,
+}
+
+/// What happened in a [`MouseEvent`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MouseEventKind {
+    /// A button was pressed or the wheel was turned.
+    Press,
+    /// A button was released.
+    Release,
+    /// The mouse was moved while a button was held.
+    Drag,
+    /// The mouse was moved without a button held.
+    Move,
+}
+
+/// A mouse report sent by the terminal.
+///
+/// Positions are 0-based like in [`Term::move_cursor_to`](crate::Term::move_cursor_to).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct MouseEvent {
+    /// What happened.
+    pub kind: MouseEventKind,
+    /// The button that was pressed, released or held.
+    pub button: MouseButton,
+    /// The column of the mouse pointer.
+    pub column: u16,
+    /// The row of the mouse pointer.
+    pub row: u16,
+    /// The modifiers that were held.
+    pub modifiers: KeyModifiers,
+}
+
+impl MouseEvent {
+    /// Decodes the parameters and final byte of an SGR (1006) mouse report.
+    fn from_sgr(params: &[u8], final_byte: u8) -> Option<Self> {
+        let mut values = core::str::from_utf8(params.strip_prefix(b"<")?)
+            .ok()?
+            .split(';')
+            .map(|value| value.parse::<u16>().ok());
+        let (code, column, row) = (values.next()??, values.next()??, values.next()??);
+        if values.next().is_some() {
+            return None;
+        }
+
+        let button = match (code & 0xc3, code & 0x40 != 0) {
+            (0, false) => MouseButton::Left,
+            (1, false) => MouseButton::Middle,
+            (2, false) => MouseButton::Right,
+            (3, false) => MouseButton::None,
+            (0x40, true) => MouseButton::WheelUp,
+            (0x41, true) => MouseButton::WheelDown,
+            (0x42, true) => MouseButton::WheelLeft,
+            (0x43, true) => MouseButton::WheelRight,
+            _ => MouseButton::Unknown,
+        };
+        let kind = match final_byte {
+            b'm' => MouseEventKind::Release,
+            _ if code & 0x20 == 0 => MouseEventKind::Press,
+            _ if button == MouseButton::None => MouseEventKind::Move,
+            _ => MouseEventKind::Drag,
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for (bit, modifier) in [
+            (0x04, KeyModifiers::SHIFT),
+            (0x08, KeyModifiers::ALT),
+            (0x10, KeyModifiers::CTRL),
+        ] {
+            if code & bit != 0 {
+                modifiers |= modifier;
+            }
+        }
+
+        Some(Self {
+            kind,
+            button,
+            column: column.saturating_sub(1),
+            row: row.saturating_sub(1),
+            modifiers,
+        })
+    }
+}
+
+/// Modifier keys held down while a key was pressed.
+///
+/// Modifiers can be combined with `|`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    /// No modifiers.
+    pub const NONE: Self = Self(0);
+    /// The shift key.
+    pub const SHIFT: Self = Self(1);
+    /// The alt key (or meta key).
+    pub const ALT: Self = Self(2);
+    /// The control key.
+    pub const CTRL: Self = Self(4);
+
+    /// Returns `true` if all modifiers in `other` are held.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if no modifier is held.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Decodes the modifier parameter of xterm style sequences.
+    fn from_param(param: &[u8]) -> Option<Self> {
+        let value: u8 = core::str::from_utf8(param).ok()?.parse().ok()?;
+        let bits = value.checked_sub(1)?;
+        // meta is reported separately by some terminals but means alt
+        let alt = if bits & 8 != 0 { Self::ALT.0 } else { 0 };
+        Some(Self(bits & 7 | alt))
+    }
+}
+
+impl BitOr for KeyModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for KeyModifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A key press together with the held modifiers.
+///
+/// Control characters are reported as the letter they are typed with and
+/// [`KeyModifiers::CTRL`], characters that were typed with alt are
+/// reported with [`KeyModifiers::ALT`].  Shift is only reported for keys
+/// that are not characters, as for characters it is already part of the
+/// character itself.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct KeyEvent {
+    /// The key that was pressed.
+    pub code: Key,
+    /// The modifiers that were held.
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyEvent {
+    /// Creates a new key event.
+    pub const fn new(code: Key, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+}
+
+impl From<Key> for KeyEvent {
+    /// Converts a key without modifiers, control characters are turned into
+    /// the letter they are typed with and [`KeyModifiers::CTRL`].
+    fn from(key: Key) -> Self {
+        match key {
+            Key::CtrlC => Self::new(Key::Char('c'), KeyModifiers::CTRL),
+            Key::Char(c) if c.is_ascii_control() => event_from_byte(c as u8),
+            code => Self::new(code, KeyModifiers::NONE),
+        }
+    }
+}
+
+/// An input event read with [`Term::read_event`](crate::Term::read_event).
+///
+/// Mouse reports, pastes and focus changes are only sent while enabled
+/// with [`Term::capture_mouse`](crate::Term::capture_mouse),
+/// [`Term::enable_bracketed_paste`](crate::Term::enable_bracketed_paste)
+/// and [`Term::enable_focus_reporting`](crate::Term::enable_focus_reporting).
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Event {
+    /// A key was pressed.
+    Key(KeyEvent),
+    /// A mouse report.
+    Mouse(MouseEvent),
+    /// Pasted text.
+    Paste(String),
+    /// The terminal gained focus.
+    FocusGained,
+    /// The terminal lost focus.
+    FocusLost,
+    /// The terminal was resized to the given rows and columns.
+    Resize(u16, u16),
+}
+
+impl From<KeyEvent> for Event {
+    /// Converts a key event, moving the keys that are not key presses to
+    /// their own variants.
+    fn from(event: KeyEvent) -> Self {
+        match event.code {
+            Key::Mouse(mouse) => Self::Mouse(mouse),
+            Key::Paste(text) => Self::Paste(text),
+            Key::FocusGained => Self::FocusGained,
+            Key::FocusLost => Self::FocusLost,
+            _ => Self::Key(event),
+        }
+    }
+}
+
+/// Decodes the bytes sent by a terminal into keys.
+///
+/// The decoder is a pure state machine: bytes are fed in with
+/// [`push`](Self::push) or [`push_event`](Self::push_event) as they are
+/// read and a key is returned as soon as one is complete.  It understands
+/// utf-8 and the escape sequences sent by xterm, rxvt, tmux and the linux
+/// console.
+///
+/// A lone escape key is sent the same way as the start of a longer
+/// sequence.  While [`is_lone_escape`](Self::is_lone_escape) is true the
+/// caller should only wait briefly for more input and call
+/// [`timeout`](Self::timeout) if nothing arrives.  Once a sequence has
+/// started the rest of it is already on its way, so while
+/// [`is_escape_pending`](Self::is_escape_pending) is true the caller can
+/// wait a lot longer before giving up on it.
+///
+/// ```
+/// # use console::{Key, KeyDecoder, KeyEvent, KeyModifiers};
+/// let mut decoder = KeyDecoder::new();
+/// let keys: Vec<_> = b"a\x1b[A\r".iter().filter_map(|&b| decoder.push(b)).collect();
+/// assert_eq!(keys, [Key::Char('a'), Key::ArrowUp, Key::Enter]);
+///
+/// assert_eq!(decoder.push(b'\x1b'), None);
+/// assert!(decoder.is_lone_escape());
+/// assert_eq!(decoder.timeout(), Some(Key::Escape));
+///
+/// let events: Vec<_> = b"\x17\x1b[1;5A".iter().filter_map(|&b| decoder.push_event(b)).collect();
+/// assert_eq!(events, [
+///     KeyEvent::new(Key::Char('w'), KeyModifiers::CTRL),
+///     KeyEvent::new(Key::ArrowUp, KeyModifiers::CTRL),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeyDecoder {
+    state: DecoderState,
+    alt: bool,
+    buf: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum DecoderState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Ss3,
+    Utf8(u8),
+    Paste,
+}
+
+/// Sequences longer than this are not sent by terminals and are given up.
+const MAX_SEQUENCE_LEN: usize = 32;
+
+/// Pastes are handed out in parts once this many bytes are buffered.
+const MAX_PASTE_LEN: usize = 64 * 1024;
+
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+impl KeyDecoder {
+    /// Creates a new decoder.
+    pub const fn new() -> Self {
+        Self {
+            state: DecoderState::Ground,
+            alt: false,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Feeds a single byte into the decoder.
+    ///
+    /// Returns the key if the byte completed one.  Keys that were pressed
+    /// with modifiers that [`Key`] cannot express are returned as they
+    /// were before modifiers were supported: control characters as
+    /// [`Key::Char`] and everything else as [`Key::UnknownEscSeq`].
+    pub fn push(&mut self, byte: u8) -> Option<Key> {
+        self.decode(byte).map(|(_, key)| key)
+    }
+
+    /// Feeds a single byte into the decoder.
+    ///
+    /// Returns the key and its modifiers if the byte completed a key.
+    pub fn push_event(&mut self, byte: u8) -> Option<KeyEvent> {
+        self.decode(byte).map(|(event, _)| event)
+    }
+
+    /// Returns `true` if an escape sequence was started but is not complete.
+    pub fn is_escape_pending(&self) -> bool {
+        matches!(
+            self.state,
+            DecoderState::Escape | DecoderState::Csi | DecoderState::Ss3
+        )
+    }
+
+    /// Returns `true` while a bracketed paste is being received.
+    ///
+    /// Control characters like Ctrl-C are part of the pasted text then.
+    pub fn is_paste_pending(&self) -> bool {
+        self.state == DecoderState::Paste
+    }
+
+    /// Returns `true` if only an escape was received.
+    ///
+    /// This is either the escape key or the start of an escape sequence.
+    pub fn is_lone_escape(&self) -> bool {
+        self.state == DecoderState::Escape
+    }
+
+    /// Tells the decoder that no more input arrived in time.
+    ///
+    /// A pending lone escape is returned as [`Key::Escape`] and an
+    /// incomplete escape sequence as [`Key::UnknownEscSeq`].  A paste whose
+    /// end was lost is returned as [`Key::Paste`] with the text received so
+    /// far, so that the keys after it are decoded again.  Incomplete utf-8
+    /// characters are kept as they cannot be ambiguous.
+    pub fn timeout(&mut self) -> Option<Key> {
+        self.decode_timeout().map(|(_, key)| key)
+    }
+
+    /// Like [`timeout`](Self::timeout) but returns a key event.
+    ///
+    /// An escape followed by `[` or `O` is reported as that character
+    /// typed with alt.
+    pub fn timeout_event(&mut self) -> Option<KeyEvent> {
+        self.decode_timeout().map(|(event, _)| event)
+    }
+
+    /// Feeds a byte and returns both the key event and the plain key.
+    pub(crate) fn decode(&mut self, byte: u8) -> Option<(KeyEvent, Key)> {
+        let rv = match self.state {
+            DecoderState::Ground => match byte {
+                b'\x1b' => {
+                    self.state = DecoderState::Escape;
+                    None
+                }
+                0x00..=0x7f => Some((event_from_byte(byte), key_from_byte(byte))),
+                _ => self.start_utf8(byte),
+            },
+            DecoderState::Utf8(_) if byte & 0xc0 != 0x80 => Some(unknown()),
+            DecoderState::Utf8(remaining) => {
+                self.buf.push(byte);
+                if remaining > 1 {
+                    self.state = DecoderState::Utf8(remaining - 1);
+                    None
+                } else {
+                    let c = core::str::from_utf8(&self.buf)
+                        .ok()
+                        .and_then(|s| s.chars().next());
+                    Some(match c {
+                        Some(c) if self.alt => (
+                            KeyEvent::new(Key::Char(c), KeyModifiers::ALT),
+                            Key::UnknownEscSeq(vec![c]),
+                        ),
+                        Some(c) => (KeyEvent::from(Key::Char(c)), Key::Char(c)),
+                        None => unknown(),
+                    })
+                }
+            }
+            DecoderState::Escape => match byte {
+                b'[' => {
+                    self.buf.push(byte);
+                    self.state = DecoderState::Csi;
+                    None
+                }
+                b'O' => {
+                    self.buf.push(byte);
+                    self.state = DecoderState::Ss3;
+                    None
+                }
+                0x00..=0x7f => {
+                    let mut event = event_from_byte(byte);
+                    event.modifiers |= KeyModifiers::ALT;
+                    Some((event, Key::UnknownEscSeq(vec![byte as char])))
+                }
+                _ => {
+                    self.alt = true;
+                    self.start_utf8(byte)
+                }
+            },
+            DecoderState::Csi => {
+                self.buf.push(byte);
+                match byte {
+                    // parameter and intermediate bytes
+                    0x20..=0x3f if self.buf.len() < MAX_SEQUENCE_LEN => None,
+                    // the linux console sends F1 to F5 as `ESC [ [ A` to `E`
+                    b'[' if self.buf.len() == 2 => None,
+                    b'~' if self.buf == b"[200~" => {
+                        self.state = DecoderState::Paste;
+                        self.buf.clear();
+                        None
+                    }
+                    0x40..=0x7e => {
+                        let event = csi_event(&self.buf[1..self.buf.len() - 1], byte);
+                        Some(self.sequence(event))
+                    }
+                    _ => Some(self.sequence(None)),
+                }
+            }
+            DecoderState::Ss3 => {
+                self.buf.push(byte);
+                let event = ss3_key(byte).map(|key| KeyEvent::new(key, KeyModifiers::NONE));
+                Some(self.sequence(event))
+            }
+            DecoderState::Paste => {
+                self.buf.push(byte);
+                if let Some(text) = self.buf.strip_suffix(PASTE_END) {
+                    Some(paste(text))
+                } else if self.buf.len() >= MAX_PASTE_LEN {
+                    // hand out the part that is complete and keep pasting
+                    let rest = self.buf.split_off(self.paste_part_len());
+                    return Some(paste(&mem::replace(&mut self.buf, rest)));
+                } else {
+                    None
+                }
+            }
+        };
+        if rv.is_some() {
+            self.reset();
+        }
+        rv
+    }
+
+    /// Resolves a pending escape sequence, see [`KeyDecoder::timeout`].
+    pub(crate) fn decode_timeout(&mut self) -> Option<(KeyEvent, Key)> {
+        let rv = match (self.state, &self.buf[..]) {
+            (DecoderState::Escape, _) => (KeyEvent::from(Key::Escape), Key::Escape),
+            (DecoderState::Csi | DecoderState::Ss3, &[c]) => (
+                KeyEvent::new(Key::Char(c as char), KeyModifiers::ALT),
+                self.unknown_sequence(),
+            ),
+            (DecoderState::Csi | DecoderState::Ss3, _) => self.sequence(None),
+            (DecoderState::Paste, text) => paste(text),
+            (DecoderState::Ground | DecoderState::Utf8(_), _) => return None,
+        };
+        self.reset();
+        Some(rv)
+    }
+
+    fn start_utf8(&mut self, byte: u8) -> Option<(KeyEvent, Key)> {
+        let remaining = match byte {
+            0xc0..=0xdf => 1,
+            0xe0..=0xef => 2,
+            0xf0..=0xf7 => 3,
+            _ => return Some(unknown()),
+        };
+        self.buf.push(byte);
+        self.state = DecoderState::Utf8(remaining);
+        None
+    }
+
+    /// Returns the decoded escape sequence or an unknown one.
+    ///
+    /// Keys with modifiers are unknown sequences as plain keys.
+    fn sequence(&self, event: Option<KeyEvent>) -> (KeyEvent, Key) {
+        match event {
+            Some(event) if event.modifiers.is_empty() => {
+                let key = event.code.clone();
+                (event, key)
+            }
+            Some(event) => (event, self.unknown_sequence()),
+            None => {
+                let key = self.unknown_sequence();
+                (KeyEvent::from(key.clone()), key)
+            }
+        }
+    }
+
+    fn unknown_sequence(&self) -> Key {
+        Key::UnknownEscSeq(self.buf.iter().map(|&b| b as char).collect())
+    }
+
+    /// Returns how much of a long paste can be handed out without cutting
+    /// a character, a line break or the end of the paste in half.
+    fn paste_part_len(&self) -> usize {
+        let buf = &self.buf[..];
+        let mut len = buf.len()
+            - (1..PASTE_END.len())
+                .rev()
+                .find(|&n| buf.ends_with(&PASTE_END[..n]))
+                .unwrap_or(0);
+        if let Some(start) = buf[..len].iter().rposition(|&b| b & 0xc0 != 0x80) {
+            let width = match buf[start] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if start + width > len {
+                len = start;
+            }
+        }
+        if buf[..len].ends_with(b"\r") {
+            len -= 1;
+        }
+        len
+    }
+
+    fn reset(&mut self) {
+        self.state = DecoderState::Ground;
+        self.alt = false;
+        self.buf.clear();
+    }
+}
+
+fn paste(text: &[u8]) -> (KeyEvent, Key) {
+    // terminals send line breaks as carriage returns
+    let text = String::from_utf8_lossy(text)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    (KeyEvent::from(Key::Paste(text.clone())), Key::Paste(text))
+}
+
+fn unknown() -> (KeyEvent, Key) {
+    (KeyEvent::from(Key::Unknown), Key::Unknown)
+}
+
+fn key_from_byte(byte: u8) -> Key {
+    match byte {
+        b'\n' | b'\r' => Key::Enter,
+        b'\x7f' => Key::Backspace,
+        b'\t' => Key::Tab,
+        b'\x01' => Key::Home,      // Control-A (home)
+        b'\x05' => Key::End,       // Control-E (end)
+        b'\x08' => Key::Backspace, // Control-H (8) (Identical to '\b')
+        _ => Key::Char(byte as char),
+    }
+}
+
+fn event_from_byte(byte: u8) -> KeyEvent {
+    let code = match byte {
+        b'\n' | b'\r' => Key::Enter,
+        b'\x7f' | b'\x08' => Key::Backspace,
+        b'\t' => Key::Tab,
+        b'\x1b' => Key::Escape,
+        // control characters are typed with control and the character
+        // 64 positions later, letters are reported in lowercase.
+        b'\x00' => return KeyEvent::new(Key::Char(' '), KeyModifiers::CTRL),
+        b'\x01'..=b'\x1a' => {
+            return KeyEvent::new(Key::Char((byte + 0x60) as char), KeyModifiers::CTRL)
+        }
+        b'\x1c'..=b'\x1f' => {
+            return KeyEvent::new(Key::Char((byte + 0x40) as char), KeyModifiers::CTRL)
+        }
+        _ => Key::Char(byte as char),
+    };
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn csi_event(params: &[u8], final_byte: u8) -> Option<KeyEvent> {
+    if let (Some(b'<'), b'M' | b'm') = (params.first(), final_byte) {
+        // mouse modifiers are part of the mouse event
+        let event = MouseEvent::from_sgr(params, final_byte)?;
+        return Some(KeyEvent::new(Key::Mouse(event), KeyModifiers::NONE));
+    }
+    // xterm reports modifiers as the second parameter
+    let (params, modifiers) = match params.iter().position(|&b| b == b';') {
+        Some(idx) => (
+            &params[..idx],
+            KeyModifiers::from_param(&params[idx + 1..])?,
+        ),
+        None => (params, KeyModifiers::NONE),
+    };
+    let code = match (params, final_byte) {
+        (b"" | b"1", b'A') => Key::ArrowUp,
+        (b"" | b"1", b'B') => Key::ArrowDown,
+        (b"" | b"1", b'C') => Key::ArrowRight,
+        (b"" | b"1", b'D') => Key::ArrowLeft,
+        (b"" | b"1", b'H') => Key::Home,
+        (b"" | b"1", b'F') => Key::End,
+        (b"" | b"1", b'P'..=b'S') => Key::F(final_byte - b'P' + 1),
+        (b"[", b'A'..=b'E') => Key::F(final_byte - b'A' + 1), // linux console
+        (b"", b'Z') => Key::BackTab,
+        (b"", b'I') => Key::FocusGained,
+        (b"", b'O') => Key::FocusLost,
+        (b"1", b'~') => Key::Home, // tmux
+        (b"2", b'~') => Key::Insert,
+        (b"3", b'~') => Key::Del,
+        (b"4", b'~') => Key::End, // tmux
+        (b"5", b'~') => Key::PageUp,
+        (b"6", b'~') => Key::PageDown,
+        (b"7", b'~') => Key::Home,  // rxvt
+        (b"8", b'~') => Key::End,   // rxvt
+        (b"11", b'~') => Key::F(1), // rxvt
+        (b"12", b'~') => Key::F(2), // rxvt
+        (b"13", b'~') => Key::F(3), // rxvt
+        (b"14", b'~') => Key::F(4), // rxvt
+        (b"15", b'~') => Key::F(5),
+        (b"17", b'~') => Key::F(6),
+        (b"18", b'~') => Key::F(7),
+        (b"19", b'~') => Key::F(8),
+        (b"20", b'~') => Key::F(9),
+        (b"21", b'~') => Key::F(10),
+        (b"23", b'~') => Key::F(11),
+        (b"24", b'~') => Key::F(12),
+        _ => return None,
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Keys sent by xterm in application cursor mode and F1 to F4.
+fn ss3_key(byte: u8) -> Option<Key> {
+    Some(match byte {
+        b'P'..=b'S' => Key::F(byte - b'P' + 1),
+        b'A' => Key::ArrowUp,
+        b'B' => Key::ArrowDown,
+        b'C' => Key::ArrowRight,
+        b'D' => Key::ArrowLeft,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    fn decode(input: &[u8]) -> Vec<Key> {
+        let mut decoder = KeyDecoder::new();
+        let mut keys: Vec<Key> = input.iter().filter_map(|&b| decoder.push(b)).collect();
+        keys.extend(decoder.timeout());
+        keys
+    }
+
+    #[test]
+    fn test_sequences() {
+        let table: &[(&str, &[u8], Key)] = &[
+            ("xterm up", b"\x1b[A", Key::ArrowUp),
+            ("xterm down", b"\x1b[B", Key::ArrowDown),
+            ("xterm right", b"\x1b[C", Key::ArrowRight),
+            ("xterm left", b"\x1b[D", Key::ArrowLeft),
+            ("xterm home", b"\x1b[H", Key::Home),
+            ("xterm end", b"\x1b[F", Key::End),
+            ("xterm app up", b"\x1bOA", Key::ArrowUp),
+            ("xterm app home", b"\x1bOH", Key::Home),
+            ("xterm app end", b"\x1bOF", Key::End),
+            ("xterm backtab", b"\x1b[Z", Key::BackTab),
+            ("xterm insert", b"\x1b[2~", Key::Insert),
+            ("xterm delete", b"\x1b[3~", Key::Del),
+            ("xterm page up", b"\x1b[5~", Key::PageUp),
+            ("xterm page down", b"\x1b[6~", Key::PageDown),
+            ("rxvt home", b"\x1b[7~", Key::Home),
+            ("rxvt end", b"\x1b[8~", Key::End),
+            ("tmux home", b"\x1b[1~", Key::Home),
+            ("tmux end", b"\x1b[4~", Key::End),
+            ("xterm f1", b"\x1bOP", Key::F(1)),
+            ("xterm f4", b"\x1bOS", Key::F(4)),
+            ("xterm f5", b"\x1b[15~", Key::F(5)),
+            ("xterm f6", b"\x1b[17~", Key::F(6)),
+            ("xterm f10", b"\x1b[21~", Key::F(10)),
+            ("xterm f11", b"\x1b[23~", Key::F(11)),
+            ("xterm f12", b"\x1b[24~", Key::F(12)),
+            ("rxvt f1", b"\x1b[11~", Key::F(1)),
+            ("rxvt f4", b"\x1b[14~", Key::F(4)),
+            ("linux console f1", b"\x1b[[A", Key::F(1)),
+            ("linux console f5", b"\x1b[[E", Key::F(5)),
+            ("focus gained", b"\x1b[I", Key::FocusGained),
+            ("focus lost", b"\x1b[O", Key::FocusLost),
+            ("linux console home", b"\x1b[1~", Key::Home),
+            ("linux console delete", b"\x1b[3~", Key::Del),
+            ("enter", b"\r", Key::Enter),
+            ("newline", b"\n", Key::Enter),
+            ("tab", b"\t", Key::Tab),
+            ("backspace", b"\x7f", Key::Backspace),
+            ("control h", b"\x08", Key::Backspace),
+            ("control a", b"\x01", Key::Home),
+            ("control e", b"\x05", Key::End),
+            ("ascii", b"x", Key::Char('x')),
+            ("two byte utf-8", "ä".as_bytes(), Key::Char('ä')),
+            ("three byte utf-8", "€".as_bytes(), Key::Char('€')),
+            ("four byte utf-8", "🦀".as_bytes(), Key::Char('🦀')),
+        ];
+        for (name, input, key) in table {
+            assert_eq!(decode(input), core::slice::from_ref(key), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_sequences() {
+        let table: &[(&[u8], Vec<Key>)] = &[
+            (b"\x1b", vec![Key::Escape]),
+            (b"\x1b[", vec![Key::UnknownEscSeq(vec!['['])]),
+            (b"\x1bO", vec![Key::UnknownEscSeq(vec!['O'])]),
+            (b"\x1b[12", vec![Key::UnknownEscSeq(vec!['[', '1', '2'])]),
+            (b"\x1b[9~", vec![Key::UnknownEscSeq(vec!['[', '9', '~'])]),
+            (
+                b"\x1b[16~",
+                vec![Key::UnknownEscSeq(vec!['[', '1', '6', '~'])],
+            ),
+            (b"\x1b[[F", vec![Key::UnknownEscSeq(vec!['[', '[', 'F'])]),
+            (
+                b"\x1b[1[A",
+                vec![Key::UnknownEscSeq(vec!['[', '1', '[']), Key::Char('A')],
+            ),
+            (b"\x1bOx", vec![Key::UnknownEscSeq(vec!['O', 'x'])]),
+            (b"\x1bx", vec![Key::UnknownEscSeq(vec!['x'])]),
+            (
+                b"\x1b[1;2Xa",
+                vec![
+                    Key::UnknownEscSeq(vec!['[', '1', ';', '2', 'X']),
+                    Key::Char('a'),
+                ],
+            ),
+            (
+                b"\x1b[1\rb",
+                vec![Key::UnknownEscSeq(vec!['[', '1', '\r']), Key::Char('b')],
+            ),
+            (b"\xc3x\xff", vec![Key::Unknown, Key::Unknown]),
+        ];
+        for (input, keys) in table {
+            assert_eq!(decode(input), *keys, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_modifiers() {
+        const NONE: KeyModifiers = KeyModifiers::NONE;
+        const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+        const ALT: KeyModifiers = KeyModifiers::ALT;
+        const CTRL: KeyModifiers = KeyModifiers::CTRL;
+        let table: &[(&[u8], Key, KeyModifiers, Key)] = &[
+            (b"\x17", Key::Char('w'), CTRL, Key::Char('\x17')),
+            (b"\x01", Key::Char('a'), CTRL, Key::Home),
+            (b"\x00", Key::Char(' '), CTRL, Key::Char('\0')),
+            (b"\x1f", Key::Char('_'), CTRL, Key::Char('\x1f')),
+            (b"\x08", Key::Backspace, NONE, Key::Backspace),
+            (b"\r", Key::Enter, NONE, Key::Enter),
+            (b"x", Key::Char('x'), NONE, Key::Char('x')),
+            (b"X", Key::Char('X'), NONE, Key::Char('X')),
+            (b"\x1bx", Key::Char('x'), ALT, Key::UnknownEscSeq(vec!['x'])),
+            (
+                b"\x1b\x7f",
+                Key::Backspace,
+                ALT,
+                Key::UnknownEscSeq(vec!['\x7f']),
+            ),
+            (
+                b"\x1b\x02",
+                Key::Char('b'),
+                ALT | CTRL,
+                Key::UnknownEscSeq(vec!['\x02']),
+            ),
+            (
+                "\x1bä".as_bytes(),
+                Key::Char('ä'),
+                ALT,
+                Key::UnknownEscSeq(vec!['ä']),
+            ),
+            (b"\x1b[", Key::Char('['), ALT, Key::UnknownEscSeq(vec!['['])),
+            (b"\x1bO", Key::Char('O'), ALT, Key::UnknownEscSeq(vec!['O'])),
+            (
+                b"\x1b[1;5A",
+                Key::ArrowUp,
+                CTRL,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '5', 'A']),
+            ),
+            (
+                b"\x1b[1;2D",
+                Key::ArrowLeft,
+                SHIFT,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '2', 'D']),
+            ),
+            (
+                b"\x1b[1;3H",
+                Key::Home,
+                ALT,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '3', 'H']),
+            ),
+            (
+                b"\x1b[1;8F",
+                Key::End,
+                SHIFT | ALT | CTRL,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '8', 'F']),
+            ),
+            (
+                b"\x1b[1;9C",
+                Key::ArrowRight,
+                ALT,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '9', 'C']),
+            ),
+            (
+                b"\x1b[3;5~",
+                Key::Del,
+                CTRL,
+                Key::UnknownEscSeq(vec!['[', '3', ';', '5', '~']),
+            ),
+            (b"\x1b[1;1B", Key::ArrowDown, NONE, Key::ArrowDown),
+            (
+                b"\x1b[1;5P",
+                Key::F(1),
+                CTRL,
+                Key::UnknownEscSeq(vec!['[', '1', ';', '5', 'P']),
+            ),
+            (
+                b"\x1b[24;2~",
+                Key::F(12),
+                SHIFT,
+                Key::UnknownEscSeq(vec!['[', '2', '4', ';', '2', '~']),
+            ),
+        ];
+        for (input, code, modifiers, key) in table {
+            let mut decoder = KeyDecoder::new();
+            let mut events: Vec<_> = input
+                .iter()
+                .filter_map(|&b| decoder.push_event(b))
+                .collect();
+            events.extend(decoder.timeout_event());
+            assert_eq!(
+                events,
+                [KeyEvent::new(code.clone(), *modifiers)],
+                "{input:?}"
+            );
+            assert_eq!(decode(input), core::slice::from_ref(key), "{input:?}");
+        }
+
+        assert!((SHIFT | CTRL).contains(CTRL));
+        assert!(!(SHIFT | CTRL).contains(ALT | CTRL));
+        assert!(NONE.is_empty());
+        assert_eq!(
+            KeyEvent::from(Key::Char('\x17')),
+            KeyEvent::new(Key::Char('w'), CTRL)
+        );
+        assert_eq!(
+            KeyEvent::from(Key::CtrlC),
+            KeyEvent::new(Key::Char('c'), CTRL)
+        );
+        assert_eq!(KeyEvent::from(Key::Tab), KeyEvent::new(Key::Tab, NONE));
+    }
+
+    #[test]
+    fn test_mouse() {
+        fn mouse(
+            kind: MouseEventKind,
+            button: MouseButton,
+            column: u16,
+            row: u16,
+            modifiers: KeyModifiers,
+        ) -> Key {
+            Key::Mouse(MouseEvent {
+                kind,
+                button,
+                column,
+                row,
+                modifiers,
+            })
+        }
+
+        use MouseButton::*;
+        use MouseEventKind::*;
+        const NONE: KeyModifiers = KeyModifiers::NONE;
+        let table: &[(&[u8], Key)] = &[
+            (b"\x1b[<0;1;1M", mouse(Press, Left, 0, 0, NONE)),
+            (b"\x1b[<0;10;5m", mouse(Release, Left, 9, 4, NONE)),
+            (b"\x1b[<1;3;4M", mouse(Press, Middle, 2, 3, NONE)),
+            (b"\x1b[<2;300;200M", mouse(Press, Right, 299, 199, NONE)),
+            (b"\x1b[<32;5;6M", mouse(Drag, Left, 4, 5, NONE)),
+            (b"\x1b[<35;5;6M", mouse(Move, None, 4, 5, NONE)),
+            (b"\x1b[<64;2;2M", mouse(Press, WheelUp, 1, 1, NONE)),
+            (b"\x1b[<65;2;2M", mouse(Press, WheelDown, 1, 1, NONE)),
+            (b"\x1b[<66;2;2M", mouse(Press, WheelLeft, 1, 1, NONE)),
+            (b"\x1b[<128;2;2M", mouse(Press, Unknown, 1, 1, NONE)),
+            (
+                b"\x1b[<4;1;1M",
+                mouse(Press, Left, 0, 0, KeyModifiers::SHIFT),
+            ),
+            (
+                b"\x1b[<26;1;1M",
+                mouse(Press, Right, 0, 0, KeyModifiers::ALT | KeyModifiers::CTRL),
+            ),
+            (
+                b"\x1b[<0;1M",
+                Key::UnknownEscSeq(vec!['[', '<', '0', ';', '1', 'M']),
+            ),
+            (
+                b"\x1b[<0;1;1;1M",
+                Key::UnknownEscSeq(vec!['[', '<', '0', ';', '1', ';', '1', ';', '1', 'M']),
+            ),
+        ];
+        for (input, key) in table {
+            assert_eq!(decode(input), core::slice::from_ref(key), "{input:?}");
+            let mut decoder = KeyDecoder::new();
+            let events: Vec<_> = input
+                .iter()
+                .filter_map(|&b| decoder.push_event(b))
+                .collect();
+            assert_eq!(events, [KeyEvent::new(key.clone(), KeyModifiers::NONE)]);
+        }
+    }
+
+    #[test]
+    fn test_paste() {
+        let table: &[(&[u8], Vec<Key>)] = &[
+            (b"\x1b[200~\x1b[201~", vec![Key::Paste("".into())]),
+            (
+                b"a\x1b[200~one\rtwo\r\n\x1b[A\x03\x1b[201~b",
+                vec![
+                    Key::Char('a'),
+                    Key::Paste("one\ntwo\n\x1b[A\x03".into()),
+                    Key::Char('b'),
+                ],
+            ),
+            (
+                "\x1b[200~äö\x1b[20\x1b[201~".as_bytes(),
+                vec![Key::Paste("äö\x1b[20".into())],
+            ),
+            (
+                b"\x1b[201~",
+                vec![Key::UnknownEscSeq(vec!['[', '2', '0', '1', '~'])],
+            ),
+        ];
+        for (input, keys) in table {
+            assert_eq!(decode(input), *keys, "{input:?}");
+        }
+
+        let mut decoder = KeyDecoder::new();
+        for &byte in b"\x1b[200~\x1b" {
+            assert_eq!(decoder.push(byte), None);
+        }
+        assert!(!decoder.is_escape_pending());
+        assert!(decoder.is_paste_pending());
+        assert_eq!(decoder.timeout(), Some(Key::Paste("\x1b".into())));
+        assert!(!decoder.is_paste_pending());
+
+        // long pastes are split up without cutting characters or line breaks
+        let long = "x".repeat(MAX_PASTE_LEN - 1);
+        let input = format!("\x1b[200~{long}é\r\n\x1b[201~a");
+        assert_eq!(
+            decode(input.as_bytes()),
+            [Key::Paste(long), Key::Paste("é\n".into()), Key::Char('a')]
+        );
+        let long = "x".repeat(MAX_PASTE_LEN - 1);
+        let input = format!("\x1b[200~{long}\r\n\x1b[201~");
+        assert_eq!(
+            decode(input.as_bytes()),
+            [Key::Paste(long), Key::Paste("\n".into())]
+        );
+        let long = "x".repeat(MAX_PASTE_LEN - 3);
+        let input = format!("\x1b[200~{long}\x1b[201~");
+        assert_eq!(
+            decode(input.as_bytes()),
+            [Key::Paste(long), Key::Paste("".into())]
+        );
+
+        // a paste without an end is handed out in parts until a timeout ends it
+        let mut decoder = KeyDecoder::new();
+        let mut keys = vec![];
+        for &byte in b"\x1b[200~unterminated"
+            .iter()
+            .chain(&[b'y'; MAX_PASTE_LEN])
+        {
+            keys.extend(decoder.push(byte));
+        }
+        assert_eq!(keys.len(), 1);
+        assert!(matches!(&keys[0], Key::Paste(text) if text.starts_with("unterminatedyyy")));
+        assert!(matches!(decoder.timeout(), Some(Key::Paste(text)) if text == "yyyyyyyyyyyy"));
+        assert_eq!(decoder.push(b'a'), Some(Key::Char('a')));
+        let keys: Vec<_> = b"\x1b[A".iter().filter_map(|&b| decoder.push(b)).collect();
+        assert_eq!(keys, [Key::ArrowUp]);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut decoder = KeyDecoder::new();
+        assert_eq!(decoder.timeout(), None);
+        assert!(!decoder.is_escape_pending());
+
+        assert_eq!(decoder.push(b'\x1b'), None);
+        assert!(decoder.is_escape_pending());
+        assert!(decoder.is_lone_escape());
+        assert_eq!(decoder.push(b'['), None);
+        assert!(decoder.is_escape_pending());
+        assert!(!decoder.is_lone_escape());
+        assert_eq!(decoder.push(b'A'), Some(Key::ArrowUp));
+        assert!(!decoder.is_escape_pending());
+
+        // incomplete utf-8 is not ambiguous
+        assert_eq!(decoder.push(0xe2), None);
+        assert!(!decoder.is_escape_pending());
+        assert_eq!(decoder.timeout(), None);
+        assert_eq!(decoder.push(0x82), None);
+        assert_eq!(decoder.push(0xac), Some(Key::Char('€')));
+
+        let long = [b'1'; MAX_SEQUENCE_LEN - 1];
+        let keys = decode(&[&b"\x1b["[..], &long[..], b"A"].concat());
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1], Key::Char('A'));
+    }
 }
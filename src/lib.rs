@@ -59,7 +59,11 @@
 //! The crate provides the function `strip_ansi_codes` to remove ANSI codes
 //! from a string as well as `measure_text_width` to calculate the width of a
 //! string as it would be displayed by the terminal.  Both of those together
-//! are useful for more complex formatting.
+//! are useful for more complex formatting.  For full control the
+//! `AnsiTokens` iterator splits a string into text, control characters
+//! and escape sequences with their parameters already parsed, while
+//! `StyledSpans` decodes colored text back into `Style` values.
+//! `ansi_to_html` and `ansi_to_svg` render colored text as HTML and SVG.
 //!
 //! # Unicode Width Support
 //!
@@ -67,11 +71,19 @@
 //! the width of terminal characters.  If you do not need this you can disable
 //! the `unicode-width` feature which will cut down on dependencies.
 //!
+//! Emoji sequences, flags and combining marks are made up of several
+//! characters that terminals draw as one.  Enabling the optional
+//! `unicode-segmentation` feature measures such grapheme clusters as a whole
+//! and makes sure truncation and padding never split them.
+//!
 //! # Features
 //!
-//! By default all features are enabled.  The following features exist:
+//! By default all features but `unicode-segmentation` are enabled.  The
+//! following features exist:
 //!
 //! * `unicode-width`: adds support for unicode width calculations
+//! * `unicode-segmentation`: measures the width of grapheme clusters instead
+//!   of single characters (implies `unicode-width`)
 //! * `ansi-parsing`: adds support for parsing ansi codes (this adds support
 //!   for stripping and taking ansi escape codes into account for length
 //!   calculations).
@@ -86,34 +98,59 @@
 extern crate alloc;
 
 #[cfg(feature = "alloc")]
-pub use crate::kb::Key;
+pub use crate::kb::{
+    Event, Key, KeyDecoder, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 #[cfg(feature = "std")]
 pub use crate::term::{
-    user_attended, user_attended_stderr, Term, TermFamily, TermFeatures, TermTarget,
+    user_attended, user_attended_stderr, Events, ModeGuard, StreamFeatures, Term, TermFamily,
+    TermFeatures, TermTarget,
 };
 #[cfg(feature = "std")]
 pub use crate::utils::{
-    colors_enabled, colors_enabled_stderr, measure_text_width, pad_str, pad_str_with,
-    set_colors_enabled, set_colors_enabled_stderr, set_true_colors_enabled,
-    set_true_colors_enabled_stderr, style, true_colors_enabled, true_colors_enabled_stderr,
-    truncate_str, Alignment, Attribute, Color, Emoji, Style, StyledObject,
+    colors_enabled, colors_enabled_stderr, expand_tabs, measure_text_width,
+    measure_text_width_with, pad_str, pad_str_with, pad_str_with_mode, set_colors_enabled,
+    set_colors_enabled_stderr, set_true_colors_enabled, set_true_colors_enabled_stderr,
+    set_width_policy, slice_str, style, true_colors_enabled, true_colors_enabled_stderr,
+    truncate_str, truncate_str_with, width_policy, wrap_lines, wrap_str, wrap_str_with, Alignment,
+    AmbiguousWidth, Attribute, Color, Emoji, Style, StyledObject, TruncateMode, WidthPolicy,
+    WrapOptions,
 };
 
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+pub use crate::ansi::StripAnsiWriter;
 #[cfg(all(feature = "ansi-parsing", feature = "alloc"))]
-pub use crate::ansi::strip_ansi_codes;
+pub use crate::ansi::{strip_ansi_codes, AnsiEvent, AnsiStreamParser};
 #[cfg(feature = "ansi-parsing")]
-pub use crate::ansi::{AnsiCodeIterator, WithoutAnsi};
+pub use crate::ansi::{
+    AnsiCodeIterator, AnsiToken, AnsiTokens, ControlString, ControlStringKind, CsiParams,
+    CsiParamsIter, CsiSequence, EscSequence, OscSequence, WithoutAnsi,
+};
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+pub use crate::html::{ansi_to_html, ansi_to_html_with, HtmlOptions};
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+pub use crate::svg::{ansi_to_svg, ansi_to_svg_with, SvgOptions};
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+pub use crate::utils::StyledSpans;
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+pub use crate::vt::{Cell, VirtualTerminal};
 
 #[cfg(feature = "std")]
 mod common_term;
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+mod html;
 #[cfg(feature = "alloc")]
 mod kb;
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+mod svg;
 #[cfg(feature = "std")]
 mod term;
 #[cfg(all(unix, not(target_arch = "wasm32"), feature = "std"))]
 mod unix_term;
 #[cfg(feature = "std")]
 mod utils;
+#[cfg(all(feature = "ansi-parsing", feature = "std"))]
+mod vt;
 #[cfg(all(feature = "std", target_arch = "wasm32"))]
 mod wasm_term;
 #[cfg(all(feature = "std", windows))]
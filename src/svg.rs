@@ -0,0 +1,347 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::ansi::{strip_ansi_codes, AnsiCodeIterator, AnsiToken};
+use crate::html::push_escaped;
+use crate::utils::{current_width_policy, expand_tabs, Attribute, Color, Style};
+use crate::vt::{Cell, VirtualTerminal};
+
+/// Options for rendering ansi colored text to SVG with [`ansi_to_svg_with`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SvgOptions<'a> {
+    font_family: &'a str,
+    font_size: u32,
+    cell_width: u32,
+    cell_height: u32,
+    padding: u32,
+    foreground: Color,
+    background: Color,
+}
+
+impl Default for SvgOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> SvgOptions<'a> {
+    /// Creates the default options.
+    ///
+    /// This renders white on black text with a 14px monospace font in
+    /// cells of 8x18 pixels.
+    pub const fn new() -> Self {
+        Self {
+            font_family: "monospace",
+            font_size: 14,
+            cell_width: 8,
+            cell_height: 18,
+            padding: 10,
+            foreground: Color::White,
+            background: Color::Black,
+        }
+    }
+
+    /// Sets the font family.
+    pub const fn font_family(mut self, font_family: &'a str) -> Self {
+        self.font_family = font_family;
+        self
+    }
+
+    /// Sets the font size in pixels.
+    pub const fn font_size(mut self, font_size: u32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets the size of a single terminal cell in pixels.
+    pub const fn cell_size(mut self, width: u32, height: u32) -> Self {
+        self.cell_width = width;
+        self.cell_height = height;
+        self
+    }
+
+    /// Sets the space around the text in pixels.
+    pub const fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the default foreground color.
+    pub const fn foreground(mut self, color: Color) -> Self {
+        self.foreground = color;
+        self
+    }
+
+    /// Sets the default background color.
+    pub const fn background(mut self, color: Color) -> Self {
+        self.background = color;
+        self
+    }
+}
+
+/// Renders ansi colored text to an SVG image.
+///
+/// This is a shortcut for [`ansi_to_svg_with`] with the default options.
+pub fn ansi_to_svg(s: &str) -> String {
+    ansi_to_svg_with(s, &SvgOptions::new())
+}
+
+/// Renders ansi colored text to an SVG image according to the given options.
+///
+/// The text is laid out on a grid of monospace cells like a terminal would
+/// show it: every line of the input is a row, wide characters take two
+/// cells and tabs are expanded to multiples of eight.  Colors are taken
+/// from the default xterm palette and the bold, dim, italic, underlined,
+/// strikethrough, reverse and hidden attributes are honored.  Carriage
+/// returns go back to the first column and the text after them replaces
+/// the cells it is written to, other escape codes and control characters
+/// are ignored.
+///
+/// The output only depends on the input and the options which makes it
+/// suitable for snapshot tests and generated documentation.
+///
+/// ```
+/// # use console::{ansi_to_svg, style};
+/// let svg = ansi_to_svg(&style("ok").green().force_styling(true).to_string());
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains("fill=\"#00cd00\""));
+/// ```
+pub fn ansi_to_svg_with(s: &str, options: &SvgOptions<'_>) -> String {
+    let (cw, ch) = (options.cell_width, options.cell_height);
+    let expanded = expand_tabs(s, 8);
+    let is_ignored = |c: char| c.is_control() && c != '\n' && c != '\r';
+
+    // Size the grid like the terminal measures chars so that nothing wraps
+    let (mut rows, mut columns) = (0, 0);
+    {
+        let policy = current_width_policy();
+        for line in strip_ansi_codes(&expanded).split('\n') {
+            rows += 1;
+            for part in line.split('\r') {
+                let chars = part.chars().filter(|c| !c.is_control());
+                columns = columns.max(chars.map(|c| policy.char_width(c)).sum());
+            }
+        }
+    }
+
+    let mut vt = VirtualTerminal::new(columns.max(1), rows);
+    for (item, is_ansi) in AnsiCodeIterator::new(&expanded) {
+        if !is_ansi {
+            vt.feed(item.replace(is_ignored, "").as_bytes());
+            continue;
+        }
+        match AnsiToken::from_escape(item) {
+            AnsiToken::Csi(csi) if csi.final_char == 'm' => vt.feed(item.as_bytes()),
+            _ => {}
+        }
+    }
+
+    let mut body = String::new();
+    for y in 0..rows {
+        let mut x = 0;
+        while x < columns {
+            let style = vt.cell(x, y).map(Cell::style).cloned().unwrap_or_default();
+            let (start, mut text) = (x, String::new());
+            while let Some(cell) = vt.cell(x, y).filter(|cell| *cell.style() == style) {
+                text.push_str(cell.text());
+                x += 1;
+            }
+            push_run(&mut body, options, &style, (start, y), x - start, &text);
+        }
+    }
+
+    let width = columns as u32 * cw + 2 * options.padding;
+    let height = rows as u32 * ch + 2 * options.padding;
+    let mut rv = String::new();
+    let _ = writeln!(
+        rv,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    );
+    rv.push_str("<style>text{font-family:");
+    push_escaped(&mut rv, options.font_family);
+    let _ = writeln!(
+        rv,
+        ";font-size:{}px;white-space:pre}}</style>",
+        options.font_size
+    );
+    let _ = writeln!(
+        rv,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        hex(options.background)
+    );
+    rv.push_str(&body);
+    rv.push_str("</svg>\n");
+    rv
+}
+
+/// Renders a run of cells with the same style.
+fn push_run(
+    body: &mut String,
+    options: &SvgOptions<'_>,
+    style: &Style,
+    (col, row): (usize, usize),
+    cells: usize,
+    text: &str,
+) {
+    let (cw, ch) = (options.cell_width, options.cell_height);
+    let x = options.padding + col as u32 * cw;
+    let y = options.padding + row as u32 * ch;
+    let (mut fg, mut bg) = (style.fg_color(), style.bg_color());
+    if style.has_attr(Attribute::Reverse) {
+        (fg, bg) = (
+            Some(bg.unwrap_or(options.background)),
+            Some(fg.unwrap_or(options.foreground)),
+        );
+    }
+    if let Some(bg) = bg {
+        let _ = writeln!(
+            body,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{}\" height=\"{ch}\" fill=\"{}\"/>",
+            cells as u32 * cw,
+            hex(bg)
+        );
+    }
+
+    let decorations: String = [
+        (Attribute::Underlined, " underline"),
+        (Attribute::StrikeThrough, " line-through"),
+    ]
+    .iter()
+    .filter(|(attr, _)| style.has_attr(*attr))
+    .map(|(_, value)| *value)
+    .collect();
+    // Trailing blanks only matter if they are decorated
+    let (text, cells) = if decorations.is_empty() {
+        let trimmed = text.trim_end_matches(' ');
+        (trimmed, cells - (text.len() - trimmed.len()))
+    } else {
+        (text, cells)
+    };
+    if cells == 0 || (text.trim().is_empty() && decorations.is_empty()) {
+        return;
+    }
+    if style.has_attr(Attribute::Hidden) {
+        return;
+    }
+
+    let _ = write!(
+        body,
+        "<text x=\"{x}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\"",
+        y + (ch + options.font_size) / 2 - options.font_size / 5,
+        cells as u32 * cw,
+        hex(fg.unwrap_or(options.foreground)),
+    );
+    if style.has_attr(Attribute::Bold) {
+        body.push_str(" font-weight=\"bold\"");
+    }
+    if style.has_attr(Attribute::Italic) {
+        body.push_str(" font-style=\"italic\"");
+    }
+    if style.has_attr(Attribute::Dim) {
+        body.push_str(" opacity=\"0.5\"");
+    }
+    if !decorations.is_empty() {
+        let _ = write!(body, " text-decoration=\"{}\"", &decorations[1..]);
+    }
+    body.push('>');
+    push_escaped(body, text);
+    body.push_str("</text>\n");
+}
+
+fn hex(color: Color) -> String {
+    let (r, g, b) = color.rgb();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::style;
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            ansi_to_svg(""),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"38\" viewBox=\"0 0 20 38\">\n\
+             <style>text{font-family:monospace;font-size:14px;white-space:pre}</style>\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        let options = SvgOptions::new()
+            .cell_size(10, 20)
+            .font_size(16)
+            .padding(0)
+            .font_family("\"Fira Code\"")
+            .foreground(Color::Color256(15))
+            .background(Color::TrueColor(1, 2, 3));
+        let s = format!(
+            "a<b\n\t{}\r\n{}",
+            style("x").red().on_blue().bold().force_styling(true),
+            style("y z")
+                .italic()
+                .underlined()
+                .strikethrough()
+                .dim()
+                .force_styling(true),
+        );
+        assert_eq!(
+            ansi_to_svg_with(&s, &options),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"90\" height=\"60\" viewBox=\"0 0 90 60\">\n\
+             <style>text{font-family:&quot;Fira Code&quot;;font-size:16px;white-space:pre}</style>\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"#010203\"/>\n\
+             <text x=\"0\" y=\"15\" textLength=\"30\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#ffffff\">a&lt;b</text>\n\
+             <rect x=\"80\" y=\"20\" width=\"10\" height=\"20\" fill=\"#0000ee\"/>\n\
+             <text x=\"80\" y=\"35\" textLength=\"10\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#cd0000\" font-weight=\"bold\">x</text>\n\
+             <text x=\"0\" y=\"55\" textLength=\"30\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#ffffff\" font-style=\"italic\" opacity=\"0.5\" text-decoration=\"underline line-through\">y z</text>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn test_reverse_and_hidden() {
+        let options = SvgOptions::new().padding(0);
+        let svg = ansi_to_svg_with("\x1b[7mab\x1b[0m\x1b[8mcd\x1b[0m  \x1b[42m  ", &options);
+        let body: Vec<_> = svg.lines().skip(3).collect();
+        assert_eq!(
+            body,
+            [
+                "<rect x=\"0\" y=\"0\" width=\"16\" height=\"18\" fill=\"#e5e5e5\"/>",
+                "<text x=\"0\" y=\"14\" textLength=\"16\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#000000\">ab</text>",
+                "<rect x=\"48\" y=\"0\" width=\"16\" height=\"18\" fill=\"#00cd00\"/>",
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_carriage_return() {
+        let options = SvgOptions::new().padding(0);
+        let svg = ansi_to_svg_with("abc\rd\x1b[1me\r\n", &options);
+        let body: Vec<_> = svg.lines().skip(3).collect();
+        assert_eq!(
+            body,
+            [
+                "<text x=\"0\" y=\"14\" textLength=\"8\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#e5e5e5\">d</text>",
+                "<text x=\"8\" y=\"14\" textLength=\"8\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#e5e5e5\" font-weight=\"bold\">e</text>",
+                "<text x=\"16\" y=\"14\" textLength=\"8\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#e5e5e5\">c</text>",
+                "</svg>",
+            ]
+        );
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"36\"")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn test_wide_chars() {
+        let svg = ansi_to_svg_with("日本\nab", &SvgOptions::new().padding(0));
+        assert!(svg.contains("width=\"32\" height=\"36\""));
+        assert!(svg
+            .contains("textLength=\"32\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#e5e5e5\">日本"));
+    }
+}
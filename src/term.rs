@@ -1,13 +1,20 @@
 use alloc::sync::Arc;
 use core::fmt::{Debug, Display};
+use core::iter::FusedIterator;
+use core::time::Duration;
 use std::io::{self, Read, Write};
 #[cfg(any(unix, all(target_os = "wasi", target_env = "p1")))]
 use std::os::fd::{AsRawFd, RawFd};
 #[cfg(windows)]
 use std::os::windows::io::{AsRawHandle, RawHandle};
 use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::Instant;
 
-use crate::{kb::Key, utils::Style};
+use crate::{
+    kb::{Event, Key, KeyDecoder, KeyEvent},
+    utils::Style,
+};
 
 #[cfg(unix)]
 trait TermWrite: Write + Debug + AsRawFd + Send {}
@@ -22,12 +29,74 @@ impl<T: Read + Debug + AsRawFd + Send> TermRead for T {}
 #[cfg(unix)]
 #[derive(Debug, Clone)]
 pub struct ReadWritePair {
-    #[allow(unused)]
     read: Arc<Mutex<dyn TermRead>>,
     write: Arc<Mutex<dyn TermWrite>>,
     style: Style,
 }
 
+trait StreamWrite: Write + Debug + Send {}
+impl<T: Write + Debug + Send> StreamWrite for T {}
+
+trait StreamRead: Read + Debug + Send {}
+impl<T: Read + Debug + Send> StreamRead for T {}
+
+#[derive(Debug, Clone)]
+pub struct ReadWriteStream {
+    read: Arc<Mutex<dyn StreamRead>>,
+    write: Arc<Mutex<dyn StreamWrite>>,
+    style: Style,
+    features: StreamFeatures,
+}
+
+/// The features a terminal created with [`Term::read_write_stream`]
+/// pretends to have.
+///
+/// As such a terminal is not backed by a real device nothing can be
+/// detected and all features are off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamFeatures {
+    is_attended: bool,
+    size: Option<(u16, u16)>,
+    colors_supported: bool,
+    true_colors_supported: bool,
+}
+
+impl StreamFeatures {
+    /// Creates features for a stream that is not a terminal.
+    pub const fn new() -> Self {
+        Self {
+            is_attended: false,
+            size: None,
+            colors_supported: false,
+            true_colors_supported: false,
+        }
+    }
+
+    /// Sets whether the stream pretends to be a user attended terminal.
+    pub const fn attended(mut self, yes: bool) -> Self {
+        self.is_attended = yes;
+        self
+    }
+
+    /// Sets the size in rows and columns the terminal reports.
+    pub const fn size(mut self, rows: u16, cols: u16) -> Self {
+        self.size = Some((rows, cols));
+        self
+    }
+
+    /// Sets whether colors are supported.
+    pub const fn colors_supported(mut self, yes: bool) -> Self {
+        self.colors_supported = yes;
+        self
+    }
+
+    /// Sets whether true colors are supported.
+    pub const fn true_colors_supported(mut self, yes: bool) -> Self {
+        self.true_colors_supported = yes;
+        self
+    }
+}
+
 /// Where the term is writing.
 #[derive(Debug, Clone)]
 pub enum TermTarget {
@@ -35,6 +104,7 @@ pub enum TermTarget {
     Stderr,
     #[cfg(unix)]
     ReadWritePair(ReadWritePair),
+    ReadWriteStream(ReadWriteStream),
 }
 
 #[derive(Debug)]
@@ -43,6 +113,13 @@ struct TermInner {
     buffer: Option<Mutex<Vec<u8>>>,
     prompt: RwLock<String>,
     prompt_guard: Mutex<()>,
+    last_size: Mutex<Option<Option<(u16, u16)>>>,
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    resizes_seen: Mutex<Option<usize>>,
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    key_reader: Mutex<KeyReader>,
+    /// The number of guards that keep each [`Mode`] enabled.
+    mode_guards: Mutex<[usize; 4]>,
 }
 
 impl TermInner {
@@ -60,6 +137,12 @@ impl TermInner {
             buffer: buffer.map(Mutex::new),
             prompt: RwLock::new(String::new()),
             prompt_guard: Mutex::new(()),
+            last_size: Mutex::new(None),
+            #[cfg(all(unix, not(target_arch = "wasm32")))]
+            resizes_seen: Mutex::new(None),
+            #[cfg(all(unix, not(target_arch = "wasm32")))]
+            key_reader: Mutex::new(KeyReader::default()),
+            mode_guards: Mutex::new([0; 4]),
         }
     }
 }
@@ -85,7 +168,10 @@ impl TermFeatures<'_> {
     /// Check if this is a real user attended terminal (`isatty`)
     #[inline]
     pub fn is_attended(&self) -> bool {
-        is_a_terminal(self.0)
+        match self.0.stream_features() {
+            Some(features) => features.is_attended,
+            None => is_a_terminal(self.0),
+        }
     }
 
     /// Check if colors are supported by this terminal.
@@ -94,12 +180,18 @@ impl TermFeatures<'_> {
     /// are considered to support colors
     #[inline]
     pub fn colors_supported(&self) -> bool {
-        is_a_color_terminal(self.0)
+        match self.0.stream_features() {
+            Some(features) => features.colors_supported,
+            None => is_a_color_terminal(self.0),
+        }
     }
 
     /// Check if true colors are supported by this terminal.
     pub fn true_colors_supported(&self) -> bool {
-        is_a_true_color_terminal(self.0)
+        match self.0.stream_features() {
+            Some(features) => features.true_colors_supported,
+            None => is_a_true_color_terminal(self.0),
+        }
     }
 
     /// Check if this terminal is an msys terminal.
@@ -110,7 +202,7 @@ impl TermFeatures<'_> {
     pub fn is_msys_tty(&self) -> bool {
         #[cfg(windows)]
         {
-            msys_tty_on(self.0)
+            self.0.stream_features().is_none() && msys_tty_on(self.0)
         }
         #[cfg(not(windows))]
         {
@@ -166,6 +258,11 @@ impl Term {
 
         term.is_msys_tty = term.features().is_msys_tty();
         term.is_tty = term.features().is_attended();
+        // streams can only be driven with escape codes, which is what the
+        // windows backend does for msys terminals.
+        if cfg!(windows) && term.stream_features().is_some() {
+            term.is_msys_tty = true;
+        }
         term
     }
 
@@ -215,6 +312,51 @@ impl Term {
         })))
     }
 
+    /// Return a terminal for the given Read/Write streams styled like
+    /// stderr.
+    ///
+    /// Unlike [`Term::read_write_pair`] the streams do not need to be
+    /// backed by a file descriptor, which makes this work with in-memory
+    /// buffers or network streams on all platforms.  As nothing can be
+    /// detected about such streams the terminal reports the given
+    /// `features` instead.
+    ///
+    /// ```
+    /// # use console::{StreamFeatures, Term};
+    /// let features = StreamFeatures::new().attended(true).size(24, 80);
+    /// let term = Term::read_write_stream(std::io::empty(), Vec::new(), features);
+    /// assert!(term.is_term());
+    /// assert_eq!(term.size(), (24, 80));
+    /// ```
+    pub fn read_write_stream<R, W>(read: R, write: W, features: StreamFeatures) -> Term
+    where
+        R: Read + Debug + Send + 'static,
+        W: Write + Debug + Send + 'static,
+    {
+        Self::read_write_stream_with_style(read, write, features, Style::new().for_stderr())
+    }
+
+    /// Return a terminal for the given Read/Write streams.
+    pub fn read_write_stream_with_style<R, W>(
+        read: R,
+        write: W,
+        features: StreamFeatures,
+        style: Style,
+    ) -> Term
+    where
+        R: Read + Debug + Send + 'static,
+        W: Write + Debug + Send + 'static,
+    {
+        Term::with_inner(TermInner::new(TermTarget::ReadWriteStream(
+            ReadWriteStream {
+                read: Arc::new(Mutex::new(read)),
+                write: Arc::new(Mutex::new(write)),
+                style,
+                features,
+            },
+        )))
+    }
+
     /// Return the style for this terminal.
     #[inline]
     pub fn style(&self) -> Style {
@@ -223,6 +365,7 @@ impl Term {
             TermTarget::Stdout => Style::new().for_stdout(),
             #[cfg(unix)]
             TermTarget::ReadWritePair(ReadWritePair { ref style, .. }) => style.clone(),
+            TermTarget::ReadWriteStream(ReadWriteStream { ref style, .. }) => style.clone(),
         }
     }
 
@@ -286,12 +429,14 @@ impl Term {
     /// Read a single key from the terminal.
     ///
     /// This does not echo anything.  If the terminal is not user attended
-    /// the return value will always be the unknown key.
+    /// the return value will always be the unknown key.  Terminals created
+    /// with [`Term::read_write_pair`] read from their own reader and put it
+    /// into raw mode while reading if it is a terminal.
     pub fn read_key(&self) -> io::Result<Key> {
         if !self.is_tty {
             Ok(Key::Unknown)
         } else {
-            read_single_key(false)
+            self.read_single_key(false).map(|(_, key)| key)
         }
     }
 
@@ -299,7 +444,110 @@ impl Term {
         if !self.is_tty {
             Ok(Key::Unknown)
         } else {
-            read_single_key(true)
+            self.read_single_key(true).map(|(_, key)| key)
+        }
+    }
+
+    /// Read a single key, waiting at most for the given timeout.
+    ///
+    /// Returns `None` if no key was pressed in time.  Like
+    /// [`read_key`](Self::read_key) this does not echo anything and keeps
+    /// the terminal in raw mode while waiting, so that a key press is seen
+    /// immediately.  A key that was only partly received when the timeout
+    /// ended is completed by the next read.  When calling this in a loop
+    /// hold the guard returned by [`enable_raw_mode`](Self::enable_raw_mode),
+    /// so that the terminal also stays in raw mode between the calls.  If
+    /// the terminal is not user attended the return value will always be
+    /// `None`.
+    pub fn read_key_timeout(&self, timeout: Duration) -> io::Result<Option<Key>> {
+        if !self.is_tty {
+            Ok(None)
+        } else {
+            self.read_single_key_timeout(false, Some(timeout), None)
+                .map(|key| key.map(|(_, key)| key))
+        }
+    }
+
+    /// Read a single key if one was already pressed.
+    ///
+    /// This does not block and is a shortcut for
+    /// [`read_key_timeout`](Self::read_key_timeout) with a zero timeout.
+    #[inline]
+    pub fn poll_key(&self) -> io::Result<Option<Key>> {
+        self.read_key_timeout(Duration::ZERO)
+    }
+
+    /// Read a single key together with the held modifiers.
+    ///
+    /// This is like [`read_key`](Self::read_key) but reports keys pressed
+    /// with control, alt or shift as [`KeyEvent`]s instead of control
+    /// characters and unknown escape sequences.  On Windows only control
+    /// characters are reported with modifiers.  If the terminal is not user
+    /// attended the return value will always be the unknown key.
+    pub fn read_key_event(&self) -> io::Result<KeyEvent> {
+        if !self.is_tty {
+            Ok(KeyEvent::from(Key::Unknown))
+        } else {
+            self.read_single_key(false).map(|(event, _)| event)
+        }
+    }
+
+    /// Read the next input event.
+    ///
+    /// This is like [`read_key_event`](Self::read_key_event) but reports
+    /// mouse reports, pastes and focus changes as their own [`Event`]s.  On
+    /// unix resizes of the terminal are reported as [`Event::Resize`],
+    /// starting with the first call on this terminal.
+    /// This way an application can handle all input in a single loop.  If
+    /// the terminal is not user attended the return value will always be
+    /// the unknown key.
+    pub fn read_event(&self) -> io::Result<Event> {
+        if !self.is_tty {
+            return Ok(Event::Key(KeyEvent::from(Key::Unknown)));
+        }
+        // without a timeout there always is an event
+        self.read_single_event(None, self.watch_resizes().as_ref())
+            .map(|event| event.unwrap_or(Event::Key(KeyEvent::from(Key::Unknown))))
+    }
+
+    /// Read the next input event, waiting at most for the given timeout.
+    ///
+    /// Returns `None` if nothing happened in time.  If the terminal is not
+    /// user attended the return value will always be `None`.
+    pub fn read_event_timeout(&self, timeout: Duration) -> io::Result<Option<Event>> {
+        if !self.is_tty {
+            Ok(None)
+        } else {
+            self.read_single_event(Some(timeout), self.watch_resizes().as_ref())
+        }
+    }
+
+    /// Return an iterator over the input events.
+    ///
+    /// Every event is read like with [`read_event`](Self::read_event), but
+    /// the iterator keeps watching for resizes for as long as it lives.  It
+    /// ends after the first error, or right away if the terminal is not user
+    /// attended.
+    ///
+    /// ```no_run
+    /// # use console::{Event, Key, Term};
+    /// # fn test() -> std::io::Result<()> {
+    /// let term = Term::stdout();
+    /// let _mouse = term.capture_mouse()?;
+    /// for event in term.events() {
+    ///     match event? {
+    ///         Event::Key(key) if key.code == Key::Escape => break,
+    ///         Event::Resize(rows, cols) => println!("resized to {rows}x{cols}"),
+    ///         event => println!("{event:?}"),
+    ///     }
+    /// }
+    /// # Ok(()) } test().unwrap();
+    /// ```
+    pub fn events(&self) -> Events<'_> {
+        Events {
+            term: self,
+            done: !self.is_tty,
+            resize: self.is_tty.then(|| self.watch_resizes()).flatten(),
         }
     }
 
@@ -324,6 +572,11 @@ impl Term {
         *self.inner.prompt.write().unwrap() = initial.to_string();
         // use a guard in order to prevent races with other calls to read_line_initial_text
         let _guard = self.inner.prompt_guard.lock().unwrap();
+        // streams only pretend to be a terminal and get no mode changes
+        let _paste = match self.inner.target {
+            TermTarget::ReadWriteStream(_) => None,
+            _ => Some(self.enable_bracketed_paste()?),
+        };
 
         self.write_str(initial)?;
 
@@ -337,11 +590,19 @@ impl Term {
                     Key::Backspace => {
                         if prefix_len < chars.len() {
                             if let Some(ch) = chars.pop() {
-                                slf.clear_chars(crate::utils::char_width(ch))?;
+                                slf.clear_chars(
+                                    crate::utils::current_width_policy().char_width(ch),
+                                )?;
                             }
                         }
                         slf.flush()?;
                     }
+                    Key::Paste(text) => {
+                        let text = sanitize_paste(&text);
+                        chars.extend(text.chars());
+                        slf.write_str(&text)?;
+                        slf.flush()?;
+                    }
                     Key::Char(chr) => {
                         chars.push(chr);
                         let mut bytes_char = [0; 4];
@@ -373,7 +634,17 @@ impl Term {
         if !self.is_tty {
             return Ok("".into());
         }
-        match read_secure() {
+        let rv = match self.inner.target {
+            #[cfg(all(unix, not(target_arch = "wasm32")))]
+            TermTarget::ReadWritePair(ReadWritePair { ref read, .. }) => {
+                read_secure_from(&mut *read.lock().unwrap())
+            }
+            TermTarget::ReadWriteStream(ReadWriteStream { ref read, .. }) => {
+                read_stream_line(&mut *read.lock().unwrap())
+            }
+            _ => read_secure(),
+        };
+        match rv {
             Ok(rv) => {
                 self.write_line("")?;
                 Ok(rv)
@@ -421,7 +692,52 @@ impl Term {
     /// If the size cannot be reliably determined `None` is returned.
     #[inline]
     pub fn size_checked(&self) -> Option<(u16, u16)> {
-        terminal_size(self)
+        match self.stream_features() {
+            Some(features) => features.size,
+            None => terminal_size(self),
+        }
+    }
+
+    /// Check if the terminal size changed since the last call.
+    ///
+    /// The first call only remembers the current size and returns `false`.
+    /// This is cheap enough to be called before every redraw so that
+    /// progress bars and tables can re-layout.  To be notified instead see
+    /// [`on_resize`](Self::on_resize).
+    pub fn size_changed(&self) -> bool {
+        let size = self.size_checked();
+        let mut last_size = self.inner.last_size.lock().unwrap();
+        let changed = last_size.is_some_and(|last_size| last_size != size);
+        *last_size = Some(size);
+        changed
+    }
+
+    /// Call `callback` with the new rows and columns whenever the terminal
+    /// is resized.
+    ///
+    /// The callback stays registered for the rest of the program.  All
+    /// callbacks run on one shared background thread, so they should return
+    /// quickly.  On unix this installs a `SIGWINCH` handler which calls a
+    /// previously installed handler as well, on windows the size is polled.
+    /// Streams have a fixed size so the callback is never called for them.
+    pub fn on_resize<F>(&self, callback: F) -> io::Result<()>
+    where
+        F: Fn(u16, u16) + Send + 'static,
+    {
+        if self.stream_features().is_some() {
+            return Ok(());
+        }
+        start_resize_thread()?;
+        let term = self.clone();
+        let mut size = term.size();
+        RESIZE_CALLBACKS.lock().unwrap().push(Box::new(move || {
+            let new_size = term.size();
+            if new_size != size {
+                size = new_size;
+                callback(size.0, size.1);
+            }
+        }));
+        Ok(())
     }
 
     /// Move the cursor to row `x` and column `y`. Values are 0-based.
@@ -513,6 +829,10 @@ impl Term {
         if !self.is_tty {
             return;
         }
+        if self.stream_features().is_some() {
+            let _ = self.write_str(&format!("\x1b]0;{title}\x07"));
+            return;
+        }
         set_title(title);
     }
 
@@ -528,8 +848,204 @@ impl Term {
         hide_cursor(self)
     }
 
+    /// Enable mouse reporting until the returned guard is dropped.
+    ///
+    /// While enabled the terminal reports clicks, drags and the mouse
+    /// wheel in the SGR (1006) format, which are returned by
+    /// [`read_key`](Self::read_key) as [`Key::Mouse`].  Windows consoles
+    /// report the mouse in a different way that is not supported.
+    pub fn capture_mouse(&self) -> io::Result<ModeGuard> {
+        ModeGuard::enable(self, Mode::MouseCapture)
+    }
+
+    /// Enable bracketed paste mode until the returned guard is dropped.
+    ///
+    /// While enabled pasted text is returned by [`read_key`](Self::read_key)
+    /// as a single [`Key::Paste`] instead of one key per character, so that
+    /// pasted line breaks cannot be mistaken for the enter key.
+    /// [`read_line`](Self::read_line) enables this on its own while it
+    /// reads from a terminal and inserts pastes as they are, except that
+    /// line breaks and tabs are turned into spaces and other control
+    /// characters are removed.
+    pub fn enable_bracketed_paste(&self) -> io::Result<ModeGuard> {
+        ModeGuard::enable(self, Mode::BracketedPaste)
+    }
+
+    /// Enable focus reporting until the returned guard is dropped.
+    ///
+    /// While enabled [`read_key`](Self::read_key) returns
+    /// [`Key::FocusGained`] and [`Key::FocusLost`] when the terminal window
+    /// gains or loses focus.  Windows consoles do not support this.
+    pub fn enable_focus_reporting(&self) -> io::Result<ModeGuard> {
+        ModeGuard::enable(self, Mode::FocusReporting)
+    }
+
+    /// Keep the terminal in raw mode until the returned guard is dropped.
+    ///
+    /// Key reads switch the terminal into raw mode and back on their own,
+    /// so keys typed between two reads are echoed and only seen once enter
+    /// is pressed.  Hold this guard while polling with
+    /// [`poll_key`](Self::poll_key) or
+    /// [`read_key_timeout`](Self::read_key_timeout) to avoid that.  Output
+    /// processing stays on, so line breaks still return the cursor.  This
+    /// has no effect on Windows and for terminals that are not backed by a
+    /// terminal device.
+    pub fn enable_raw_mode(&self) -> io::Result<ModeGuard> {
+        ModeGuard::enable(self, Mode::RawInput)
+    }
+
     // helpers
 
+    fn read_single_key(&self, ctrlc_key: bool) -> io::Result<(KeyEvent, Key)> {
+        // without a timeout there always is a key
+        self.read_single_key_timeout(ctrlc_key, None, None)
+            .map(|key| key.unwrap_or((KeyEvent::from(Key::Unknown), Key::Unknown)))
+    }
+
+    /// Keeps the terminal keys are read from in raw mode between reads.
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    fn set_raw_input(&self, enabled: bool) -> io::Result<()> {
+        if !self.is_tty {
+            return Ok(());
+        }
+        let fd = match self.inner.target {
+            TermTarget::ReadWritePair(ReadWritePair { ref read, .. }) => {
+                Some(read.lock().unwrap().as_raw_fd())
+            }
+            TermTarget::ReadWriteStream(..) => return Ok(()),
+            _ => None,
+        };
+        let mut reader = self.inner.key_reader.lock().unwrap();
+        if enabled {
+            reader.enter_raw_mode(fd)
+        } else {
+            reader.leave_raw_mode()
+        }
+    }
+
+    #[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+    fn set_raw_input(&self, _enabled: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Reads a key, stopping early with `None` if `resize` reports a resize.
+    fn read_single_key_timeout(
+        &self,
+        ctrlc_key: bool,
+        timeout: Option<Duration>,
+        resize: Option<&ResizeWatcher>,
+    ) -> io::Result<Option<(KeyEvent, Key)>> {
+        #[cfg(all(unix, not(target_arch = "wasm32")))]
+        let wake = resize.map(AsRawFd::as_raw_fd);
+        #[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+        let _ = resize;
+        match self.inner.target {
+            #[cfg(all(unix, not(target_arch = "wasm32")))]
+            TermTarget::ReadWritePair(ReadWritePair { ref read, .. }) => {
+                // holding the lock keeps concurrent readers from splitting
+                // escape sequences between them.
+                let read = read.lock().unwrap();
+                let mut reader = self.inner.key_reader.lock().unwrap();
+                reader.read_key(Some(read.as_raw_fd()), ctrlc_key, timeout, wake)
+            }
+            TermTarget::ReadWriteStream(ReadWriteStream { ref read, .. }) => {
+                read_stream_key(&mut *read.lock().unwrap(), ctrlc_key, timeout)
+            }
+            #[cfg(all(unix, not(target_arch = "wasm32")))]
+            _ => {
+                let mut reader = self.inner.key_reader.lock().unwrap();
+                reader.read_key(None, ctrlc_key, timeout, wake)
+            }
+            #[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+            _ => match timeout {
+                Some(timeout) => read_single_key_timeout(ctrlc_key, timeout),
+                None => read_single_key(ctrlc_key).map(Some),
+            }
+            .map(|key| key.map(|key| (KeyEvent::from(key.clone()), key))),
+        }
+    }
+
+    fn read_single_event(
+        &self,
+        timeout: Option<Duration>,
+        resize: Option<&ResizeWatcher>,
+    ) -> io::Result<Option<Event>> {
+        // a watcher may also wake up the read for a resize that was
+        // already reported, which must not cut the timeout short
+        let deadline = resize.and(timeout).map(|timeout| Instant::now() + timeout);
+        let mut expired = false;
+        loop {
+            if self.take_resize() {
+                let (rows, cols) = self.size();
+                return Ok(Some(Event::Resize(rows, cols)));
+            }
+            if expired {
+                return Ok(None);
+            }
+            let remaining = match deadline {
+                Some(deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+                None => timeout,
+            };
+            let key = self.read_single_key_timeout(false, remaining, resize)?;
+            if let Some((event, _)) = key {
+                return Ok(Some(Event::from(event)));
+            }
+            if let Some(resize) = resize {
+                resize.drain();
+            }
+            expired = match deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => timeout.is_some(),
+            };
+        }
+    }
+
+    /// Returns a watcher that wakes up key reads on resizes.
+    ///
+    /// Watchers are cheap to create as their pipes are reused.  Resizes
+    /// while no read is waiting are found by [`take_resize`](Self::take_resize).  Without a watcher,
+    /// for instance if too many threads are reading at once, keys are read
+    /// as usual and resizes are reported with the next read.
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    fn watch_resizes(&self) -> Option<ResizeWatcher> {
+        if self.stream_features().is_some() {
+            return None;
+        }
+        ResizeWatcher::new().ok()
+    }
+
+    #[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+    fn watch_resizes(&self) -> Option<ResizeWatcher> {
+        None
+    }
+
+    /// Returns `true` if the terminal was resized since the last call.
+    ///
+    /// The first call only starts counting and returns `false`.
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    fn take_resize(&self) -> bool {
+        if self.stream_features().is_some() {
+            return false;
+        }
+        let count = resize_count();
+        let mut seen = self.inner.resizes_seen.lock().unwrap();
+        let resized = seen.is_some_and(|seen| seen != count);
+        *seen = Some(count);
+        resized
+    }
+
+    #[cfg(not(all(unix, not(target_arch = "wasm32"))))]
+    fn take_resize(&self) -> bool {
+        false
+    }
+
+    fn stream_features(&self) -> Option<&StreamFeatures> {
+        match self.inner.target {
+            TermTarget::ReadWriteStream(ReadWriteStream { ref features, .. }) => Some(features),
+            _ => None,
+        }
+    }
+
     #[cfg(all(windows, feature = "windows-console-colors"))]
     fn write_through(&self, bytes: &[u8]) -> io::Result<()> {
         if self.is_msys_tty || !self.is_tty {
@@ -538,6 +1054,7 @@ impl Term {
             match self.inner.target {
                 TermTarget::Stdout => console_colors(self, Console::stdout()?, bytes),
                 TermTarget::Stderr => console_colors(self, Console::stderr()?, bytes),
+                TermTarget::ReadWriteStream(..) => self.write_through_common(bytes),
             }
         }
     }
@@ -563,11 +1080,212 @@ impl Term {
                 write.write_all(bytes)?;
                 write.flush()?;
             }
+            TermTarget::ReadWriteStream(ReadWriteStream { ref write, .. }) => {
+                let mut write = write.lock().unwrap();
+                write.write_all(bytes)?;
+                write.flush()?;
+            }
         }
         Ok(())
     }
 }
 
+type ResizeCallback = Box<dyn FnMut() + Send>;
+
+/// The callbacks registered with [`Term::on_resize`].
+static RESIZE_CALLBACKS: Mutex<Vec<ResizeCallback>> = Mutex::new(Vec::new());
+
+/// Starts the thread that calls the resize callbacks, unless it is already
+/// running.
+///
+/// The thread and its watcher are shared by all callbacks, so registering
+/// more callbacks does not use up more resources.
+fn start_resize_thread() -> io::Result<()> {
+    static STARTED: Mutex<bool> = Mutex::new(false);
+
+    let mut started = STARTED.lock().unwrap();
+    if *started {
+        return Ok(());
+    }
+    let watcher = ResizeWatcher::new()?;
+    thread::Builder::new()
+        .name("console-resize".into())
+        .spawn(move || {
+            while let Ok(resized) = watcher.wait(-1) {
+                if !resized {
+                    continue;
+                }
+                // callbacks may register more callbacks, so they are not
+                // called with the lock held
+                let mut callbacks = core::mem::take(&mut *RESIZE_CALLBACKS.lock().unwrap());
+                for callback in &mut callbacks {
+                    callback();
+                }
+                let mut registered = RESIZE_CALLBACKS.lock().unwrap();
+                callbacks.append(&mut registered);
+                *registered = callbacks;
+            }
+        })?;
+    *started = true;
+    Ok(())
+}
+
+/// Reads a key from a stream.
+///
+/// Streams cannot be polled, so a pending escape sequence is only resolved
+/// by the next byte or the end of the stream.  For the same reason a
+/// timeout does not limit the wait but makes the end of the stream mean
+/// that no key was pressed.
+fn read_stream_key(
+    read: &mut dyn StreamRead,
+    ctrlc_key: bool,
+    timeout: Option<Duration>,
+) -> io::Result<Option<(KeyEvent, Key)>> {
+    let mut decoder = KeyDecoder::new();
+    let mut buf = [0];
+    loop {
+        if read.read(&mut buf)? == 0 {
+            return match decoder.decode_timeout() {
+                Some(key) => Ok(Some(key)),
+                None if timeout.is_some() => Ok(None),
+                None => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Reached end of file",
+                )),
+            };
+        }
+        if buf[0] == b'\x03' && !decoder.is_paste_pending() {
+            return if ctrlc_key {
+                Ok(Some((KeyEvent::from(Key::CtrlC), Key::CtrlC)))
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "read interrupted",
+                ))
+            };
+        }
+        if let Some(key) = decoder.decode(buf[0]) {
+            return Ok(Some(key));
+        }
+    }
+}
+
+/// Prepares pasted text to be echoed and inserted into a line.
+///
+/// Control characters would move the cursor or change the state of the
+/// terminal when echoed, so line breaks and tabs become spaces and all
+/// other control characters are removed.
+fn sanitize_paste(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\n' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Reads a line from a stream without consuming anything after it.
+fn read_stream_line(read: &mut dyn StreamRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut buf = [0];
+    while read.read(&mut buf)? == 1 && buf[0] != b'\n' {
+        line.push(buf[0]);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// An iterator over the input events of a terminal.
+///
+/// This is returned by [`Term::events`].
+#[derive(Debug)]
+pub struct Events<'a> {
+    term: &'a Term,
+    done: bool,
+    resize: Option<ResizeWatcher>,
+}
+
+impl Iterator for Events<'_> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self
+            .term
+            .read_single_event(None, self.resize.as_ref())
+            .map(|event| event.unwrap_or(Event::Key(KeyEvent::from(Key::Unknown))));
+        self.done = event.is_err();
+        Some(event)
+    }
+}
+
+impl FusedIterator for Events<'_> {}
+
+/// Keeps a terminal mode enabled until it is dropped.
+///
+/// This is returned by [`Term::capture_mouse`],
+/// [`Term::enable_bracketed_paste`], [`Term::enable_focus_reporting`] and
+/// [`Term::enable_raw_mode`].
+/// Guards for the same mode are counted per terminal (including its
+/// clones), so the mode is only disabled once the last of them is dropped.
+#[derive(Debug)]
+pub struct ModeGuard {
+    term: Term,
+    mode: Mode,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    MouseCapture,
+    BracketedPaste,
+    FocusReporting,
+    RawInput,
+}
+
+impl Mode {
+    fn set(self, term: &Term, enabled: bool) -> io::Result<()> {
+        match (self, enabled) {
+            (Mode::MouseCapture, true) => enable_mouse_capture(term)?,
+            (Mode::MouseCapture, false) => disable_mouse_capture(term)?,
+            (Mode::BracketedPaste, true) => enable_bracketed_paste(term)?,
+            (Mode::BracketedPaste, false) => disable_bracketed_paste(term)?,
+            (Mode::FocusReporting, true) => enable_focus_reporting(term)?,
+            (Mode::FocusReporting, false) => disable_focus_reporting(term)?,
+            (Mode::RawInput, enabled) => term.set_raw_input(enabled)?,
+        }
+        term.flush()
+    }
+}
+
+impl ModeGuard {
+    fn enable(term: &Term, mode: Mode) -> io::Result<Self> {
+        let mut guards = term.inner.mode_guards.lock().unwrap();
+        if guards[mode as usize] == 0 {
+            mode.set(term, true)?;
+        }
+        guards[mode as usize] += 1;
+        Ok(Self {
+            term: term.clone(),
+            mode,
+        })
+    }
+}
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        let mut guards = self.term.inner.mode_guards.lock().unwrap();
+        guards[self.mode as usize] -= 1;
+        if guards[self.mode as usize] == 0 {
+            let _ = self.mode.set(&self.term, false);
+        }
+    }
+}
+
 /// A fast way to check if the application has a user attended for stdout.
 ///
 /// This means that stdout is connected to a terminal instead of a
@@ -598,6 +1316,7 @@ impl AsRawFd for Term {
             TermTarget::ReadWritePair(ReadWritePair { ref write, .. }) => {
                 write.lock().unwrap().as_raw_fd()
             }
+            TermTarget::ReadWriteStream(..) => -1,
         }
     }
 }
@@ -605,16 +1324,17 @@ impl AsRawFd for Term {
 #[cfg(windows)]
 impl AsRawHandle for Term {
     fn as_raw_handle(&self) -> RawHandle {
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
         use windows_sys::Win32::System::Console::{
             GetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
         };
 
-        unsafe {
-            GetStdHandle(match self.inner.target {
-                TermTarget::Stdout => STD_OUTPUT_HANDLE,
-                TermTarget::Stderr => STD_ERROR_HANDLE,
-            }) as RawHandle
-        }
+        let kind = match self.inner.target {
+            TermTarget::Stdout => STD_OUTPUT_HANDLE,
+            TermTarget::Stderr => STD_ERROR_HANDLE,
+            TermTarget::ReadWriteStream(..) => return INVALID_HANDLE_VALUE as RawHandle,
+        };
+        unsafe { GetStdHandle(kind) as RawHandle }
     }
 }
 
@@ -648,13 +1368,22 @@ impl Write for &Term {
 
 impl Read for Term {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        io::stdin().read(buf)
+        (&*self).read(buf)
     }
 }
 
 impl Read for &Term {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        io::stdin().read(buf)
+        match self.inner.target {
+            #[cfg(unix)]
+            TermTarget::ReadWritePair(ReadWritePair { ref read, .. }) => {
+                read.lock().unwrap().read(buf)
+            }
+            TermTarget::ReadWriteStream(ReadWriteStream { ref read, .. }) => {
+                read.lock().unwrap().read(buf)
+            }
+            _ => io::stdin().read(buf),
+        }
     }
 }
 
@@ -664,3 +1393,435 @@ pub(crate) use crate::unix_term::*;
 pub(crate) use crate::wasm_term::*;
 #[cfg(windows)]
 pub(crate) use crate::windows_term::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kb::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+    #[derive(Debug, Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn take(&self) -> String {
+            String::from_utf8(core::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Opens a pseudo terminal and returns the master and slave ends.
+    #[cfg(target_os = "linux")]
+    fn open_pty() -> (std::fs::File, std::fs::File) {
+        use std::ffi::CStr;
+        use std::os::fd::FromRawFd;
+
+        unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(master >= 0);
+            assert_eq!(libc::grantpt(master), 0);
+            assert_eq!(libc::unlockpt(master), 0);
+            let name = CStr::from_ptr(libc::ptsname(master)).to_str().unwrap();
+            let slave = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(name)
+                .unwrap();
+            (std::fs::File::from_raw_fd(master), slave)
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_read_write_pair_input() {
+        let (mut master, slave) = open_pty();
+        let term = Term::read_write_pair(slave.try_clone().unwrap(), slave);
+        assert!(term.is_term());
+
+        // the line discipline processes input as it arrives, so the pty
+        // needs to be raw before anything is written to it.
+        let termios = unsafe {
+            let mut termios = core::mem::zeroed();
+            assert_eq!(libc::tcgetattr(term.as_raw_fd(), &mut termios), 0);
+            libc::cfmakeraw(&mut termios);
+            assert_eq!(
+                libc::tcsetattr(term.as_raw_fd(), libc::TCSANOW, &termios),
+                0
+            );
+            termios
+        };
+
+        master.write_all(b"a\x1b[A\x7fxy\x7fz\r").unwrap();
+        assert_eq!(term.read_key().unwrap(), Key::Char('a'));
+        assert_eq!(term.read_key().unwrap(), Key::ArrowUp);
+        assert_eq!(term.read_key().unwrap(), Key::Backspace);
+        assert_eq!(term.read_line().unwrap(), "xz");
+
+        let restored = unsafe {
+            let mut restored = core::mem::zeroed();
+            assert_eq!(libc::tcgetattr(term.as_raw_fd(), &mut restored), 0);
+            restored
+        };
+        assert_eq!(restored.c_lflag, termios.c_lflag);
+
+        assert_eq!(term.poll_key().unwrap(), None);
+        let start = std::time::Instant::now();
+        let timeout = Duration::from_millis(50);
+        assert_eq!(term.read_key_timeout(timeout).unwrap(), None);
+        assert!(start.elapsed() >= timeout);
+
+        master.write_all(b"\x1bOP\x1b").unwrap();
+        assert_eq!(term.poll_key().unwrap(), Some(Key::F(1)));
+        assert_eq!(term.read_key_timeout(timeout).unwrap(), Some(Key::Escape));
+        assert_eq!(term.poll_key().unwrap(), None);
+
+        // ^C inside of a paste is pasted text
+        master.write_all(b"\x1b[200~a\x03b\x1b[201~").unwrap();
+        assert_eq!(term.read_key().unwrap(), Key::Paste("a\x03b".into()));
+
+        // a paste that lost its end is ended once it stalls
+        master.write_all(b"\x1b[200~ab").unwrap();
+        assert_eq!(term.read_key().unwrap(), Key::Paste("ab".into()));
+        master.write_all(b"c").unwrap();
+        assert_eq!(term.read_key().unwrap(), Key::Char('c'));
+
+        // the rest of a started sequence may arrive a little later
+        master.write_all(b"\x1b[").unwrap();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            master.write_all(b"B").unwrap();
+            master
+        });
+        assert_eq!(term.read_key().unwrap(), Key::ArrowDown);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_raw_mode_guard() {
+        let (mut master, slave) = open_pty();
+        let term = Term::read_write_pair(slave.try_clone().unwrap(), slave);
+        let lflag = || unsafe {
+            let mut termios: libc::termios = core::mem::zeroed();
+            assert_eq!(libc::tcgetattr(term.as_raw_fd(), &mut termios), 0);
+            termios.c_lflag
+        };
+        let cooked = lflag();
+        assert_ne!(cooked & libc::ICANON, 0);
+
+        // without a guard every read restores the mode
+        assert_eq!(term.poll_key().unwrap(), None);
+        assert_eq!(lflag(), cooked);
+
+        let guard = term.enable_raw_mode().unwrap();
+        let other = term.clone().enable_raw_mode().unwrap();
+        assert_eq!(lflag() & (libc::ICANON | libc::ECHO), 0);
+        drop(other);
+        assert_eq!(term.poll_key().unwrap(), None);
+        assert_eq!(lflag() & (libc::ICANON | libc::ECHO), 0);
+
+        // keys split between polls are completed by the next one
+        master.write_all(b"\x1b[").unwrap();
+        assert_eq!(term.poll_key().unwrap(), None);
+        master.write_all(b"A\xc3").unwrap();
+        assert_eq!(term.poll_key().unwrap(), Some(Key::ArrowUp));
+        assert_eq!(term.poll_key().unwrap(), None);
+        master.write_all(b"\xa9").unwrap();
+        assert_eq!(term.poll_key().unwrap(), Some(Key::Char('\u{e9}')));
+
+        // a started sequence that never ends still times out
+        master.write_all(b"\x1b[1").unwrap();
+        assert_eq!(term.poll_key().unwrap(), None);
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            term.read_key_timeout(timeout).unwrap(),
+            Some(Key::UnknownEscSeq(vec!['[', '1']))
+        );
+
+        drop(guard);
+        assert_eq!(lflag(), cooked);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_resize() {
+        let (master, slave) = open_pty();
+        let pair = || Term::read_write_pair(slave.try_clone().unwrap(), slave.try_clone().unwrap());
+        let term = pair();
+        let resize = |rows, cols| unsafe {
+            let size = libc::winsize {
+                ws_row: rows,
+                ws_col: cols,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            assert_eq!(libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size), 0);
+            libc::raise(libc::SIGWINCH);
+        };
+
+        resize(24, 80);
+        assert!(!term.size_changed());
+        assert!(!term.size_changed());
+
+        // callbacks share one watcher, so there can be any number of them
+        let (tx, rx) = std::sync::mpsc::channel();
+        for _ in 0..10 {
+            let tx = tx.clone();
+            term.on_resize(move |rows, cols| {
+                let _ = tx.send((rows, cols));
+            })
+            .unwrap();
+        }
+        resize(30, 100);
+        for _ in 0..10 {
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok((30, 100)));
+        }
+        assert!(term.size_changed());
+        assert!(!term.size_changed());
+
+        // the first read starts watching
+        assert_eq!(term.read_event_timeout(Duration::ZERO).unwrap(), None);
+        resize(40, 120);
+        assert_eq!(term.read_event().unwrap(), Event::Resize(40, 120));
+        let timeout = Duration::from_millis(10);
+        assert_eq!(term.read_event_timeout(timeout).unwrap(), None);
+
+        // reads only hold a watcher while waiting, so any number of
+        // terminals can read events and see resizes that happened between
+        // two reads
+        let terms: Vec<_> = (0..10).map(|_| pair()).collect();
+        for term in &terms {
+            assert_eq!(term.read_event_timeout(Duration::ZERO).unwrap(), None);
+        }
+        resize(50, 132);
+        for term in &terms {
+            assert_eq!(
+                term.read_event_timeout(Duration::ZERO).unwrap(),
+                Some(Event::Resize(50, 132))
+            );
+        }
+        assert_eq!(term.read_event().unwrap(), Event::Resize(50, 132));
+
+        // an event iterator keeps its watcher between reads, a wakeup for
+        // a resize that was reported by another read is skipped
+        let mut events = term.events();
+        resize(24, 80);
+        assert_eq!(term.read_event().unwrap(), Event::Resize(24, 80));
+        (&master).write_all(b"x").unwrap();
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            Event::Key(KeyEvent::from(Key::Char('x')))
+        );
+        resize(30, 100);
+        assert_eq!(events.next().unwrap().unwrap(), Event::Resize(30, 100));
+
+        // the size of streams never changes
+        let stream = Term::read_write_stream(io::empty(), io::sink(), StreamFeatures::new());
+        stream.on_resize(|_, _| unreachable!()).unwrap();
+        assert!(!stream.size_changed());
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let features = StreamFeatures::new().attended(true);
+        let input = "\x1b[<0;3;2M\x1b[200~hi\x1b[201~\x1b[I\x1b[1;5A";
+        let term = Term::read_write_stream(input.as_bytes(), io::sink(), features);
+        let events: Vec<_> = term.events().collect();
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[0].as_ref().unwrap(),
+            &Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Press,
+                button: MouseButton::Left,
+                column: 2,
+                row: 1,
+                modifiers: KeyModifiers::NONE,
+            })
+        );
+        assert_eq!(events[1].as_ref().unwrap(), &Event::Paste("hi".into()));
+        assert_eq!(events[2].as_ref().unwrap(), &Event::FocusGained);
+        assert_eq!(
+            events[3].as_ref().unwrap(),
+            &Event::Key(KeyEvent::new(Key::ArrowUp, KeyModifiers::CTRL))
+        );
+        let err = events[4].as_ref().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let term = Term::read_write_stream(&b""[..], io::sink(), features);
+        assert_eq!(term.read_event_timeout(Duration::ZERO).unwrap(), None);
+        let term = Term::read_write_stream(&b"x"[..], io::sink(), StreamFeatures::new());
+        assert_eq!(term.events().count(), 0);
+    }
+
+    #[test]
+    fn test_stream_features() {
+        let term = Term::read_write_stream(io::empty(), io::sink(), StreamFeatures::new());
+        assert!(!term.is_term());
+        assert!(!term.features().is_attended());
+        assert!(!term.features().colors_supported());
+        assert!(!term.features().is_msys_tty());
+        assert_eq!(term.features().family(), TermFamily::File);
+        assert_eq!(term.size_checked(), None);
+        assert_eq!(term.size(), (24, DEFAULT_WIDTH));
+        assert_eq!(term.read_line().unwrap(), "");
+        assert_eq!(term.poll_key().unwrap(), None);
+
+        let features = StreamFeatures::new()
+            .attended(true)
+            .size(40, 120)
+            .colors_supported(true)
+            .true_colors_supported(true);
+        let term = Term::read_write_stream(io::empty(), io::sink(), features);
+        assert!(term.is_term());
+        assert!(term.features().colors_supported());
+        assert!(term.features().true_colors_supported());
+        assert_ne!(term.features().family(), TermFamily::File);
+        assert_eq!(term.size(), (40, 120));
+    }
+
+    #[test]
+    fn test_stream_io() {
+        let buffer = SharedBuffer::default();
+        let features = StreamFeatures::new().attended(true);
+        let term = Term::read_write_stream(&b"input"[..], buffer.clone(), features);
+        term.write_line("hello").unwrap();
+        term.move_cursor_up(2).unwrap();
+        term.clear_line().unwrap();
+        term.hide_cursor().unwrap();
+        term.set_title("title");
+        assert_eq!(
+            buffer.take(),
+            "hello\n\x1b[2A\r\x1b[2K\x1b[?25l\x1b]0;title\x07"
+        );
+
+        let mut input = String::new();
+        (&term).read_to_string(&mut input).unwrap();
+        assert_eq!(input, "input");
+    }
+
+    #[test]
+    fn test_stream_input() {
+        let buffer = SharedBuffer::default();
+        let features = StreamFeatures::new().attended(true);
+        let input = "x\x1b[D\x1bab\x7fc\rpass\r\nr\x03\x03\x1b";
+        let term = Term::read_write_stream(input.as_bytes(), buffer.clone(), features);
+        assert_eq!(term.read_key().unwrap(), Key::Char('x'));
+        assert_eq!(term.read_key().unwrap(), Key::ArrowLeft);
+        assert_eq!(term.read_key().unwrap(), Key::UnknownEscSeq(vec!['a']));
+        assert_eq!(term.read_line().unwrap(), "c");
+        assert_eq!(term.read_secure_line().unwrap(), "pass");
+        assert_eq!(term.read_char().unwrap(), 'r');
+        assert_eq!(term.read_key_raw().unwrap(), Key::CtrlC);
+        let err = term.read_key().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(term.read_key().unwrap(), Key::Escape);
+        let err = term.read_key().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let input = "\x1bx\x17\x1b[1;6D\x03";
+        let term = Term::read_write_stream(input.as_bytes(), io::sink(), features);
+        assert_eq!(
+            term.read_key_event().unwrap(),
+            KeyEvent::new(Key::Char('x'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            term.read_key_event().unwrap(),
+            KeyEvent::new(Key::Char('w'), KeyModifiers::CTRL)
+        );
+        assert_eq!(
+            term.read_key_event().unwrap(),
+            KeyEvent::new(Key::ArrowLeft, KeyModifiers::CTRL | KeyModifiers::SHIFT)
+        );
+        let err = term.read_key_event().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert_eq!(term.poll_key().unwrap(), None);
+        let err = term.read_key_event().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(buffer.take(), "b\x1b[1D\x1b[0Kc\n\n");
+
+        let input = "\x1b[200~one\rtwo\t\x1b[2J\x03\u{9b}0m\x1b[201~!\r";
+        let term = Term::read_write_stream(input.as_bytes(), buffer.clone(), features);
+        assert_eq!(term.read_line().unwrap(), "one two [2J0m!");
+        assert_eq!(buffer.take(), "one two [2J0m!\n");
+    }
+
+    #[test]
+    fn test_capture_mouse() {
+        let buffer = SharedBuffer::default();
+        let features = StreamFeatures::new().attended(true);
+        let input = &b"\x1b[<0;3;2M"[..];
+        let term = Term::read_write_stream(input, buffer.clone(), features);
+        let capture = term.capture_mouse().unwrap();
+        assert_eq!(buffer.take(), "\x1b[?1000h\x1b[?1002h\x1b[?1006h");
+        assert_eq!(
+            term.read_key().unwrap(),
+            Key::Mouse(MouseEvent {
+                kind: MouseEventKind::Press,
+                button: MouseButton::Left,
+                column: 2,
+                row: 1,
+                modifiers: KeyModifiers::NONE,
+            })
+        );
+        drop(capture);
+        assert_eq!(buffer.take(), "\x1b[?1006l\x1b[?1002l\x1b[?1000l");
+    }
+
+    #[test]
+    fn test_focus_reporting() {
+        let buffer = SharedBuffer::default();
+        let features = StreamFeatures::new().attended(true);
+        let input = &b"\x1b[O\x1b[I"[..];
+        let term = Term::read_write_stream(input, buffer.clone(), features);
+        let guard = term.enable_focus_reporting().unwrap();
+        assert_eq!(buffer.take(), "\x1b[?1004h");
+        assert_eq!(term.read_key().unwrap(), Key::FocusLost);
+        assert_eq!(term.read_key().unwrap(), Key::FocusGained);
+        drop(guard);
+        assert_eq!(buffer.take(), "\x1b[?1004l");
+    }
+
+    #[test]
+    fn test_mode_guards_are_counted() {
+        let buffer = SharedBuffer::default();
+        let features = StreamFeatures::new().attended(true);
+        let term = Term::read_write_stream(io::empty(), buffer.clone(), features);
+        let first = term.enable_bracketed_paste().unwrap();
+        let second = term.clone().enable_bracketed_paste().unwrap();
+        assert_eq!(buffer.take(), "\x1b[?2004h");
+        drop(first);
+        assert_eq!(buffer.take(), "");
+        drop(second);
+        assert_eq!(buffer.take(), "\x1b[?2004l");
+        let _third = term.enable_bracketed_paste().unwrap();
+        assert_eq!(buffer.take(), "\x1b[?2004h");
+    }
+
+    #[test]
+    fn test_stream_style() {
+        let buffer = SharedBuffer::default();
+        let term = Term::read_write_stream(io::empty(), buffer.clone(), StreamFeatures::new());
+        let style = Style::new().for_stdout();
+        let styled = Term::read_write_stream_with_style(
+            io::empty(),
+            buffer.clone(),
+            StreamFeatures::new(),
+            style.clone(),
+        );
+        assert_eq!(term.style(), Style::new().for_stderr());
+        assert_eq!(styled.style(), style);
+
+        let mut clone = term.clone();
+        clone.write_all(b"a").unwrap();
+        term.write_str("b").unwrap();
+        assert_eq!(buffer.take(), "ab");
+    }
+}
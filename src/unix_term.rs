@@ -1,15 +1,16 @@
 #[cfg(target_os = "macos")]
 use core::ptr;
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use core::time::Duration;
 use core::{fmt::Display, mem, str};
 use std::env;
 use std::fs;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
 use std::os::fd::{AsRawFd, RawFd};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
 
-#[cfg(not(target_os = "macos"))]
-use std::sync::OnceLock;
-
-use crate::kb::Key;
+use crate::kb::{Key, KeyDecoder, KeyEvent};
 use crate::term::Term;
 
 pub(crate) use crate::common_term::*;
@@ -131,18 +132,54 @@ impl AsRawFd for Input<BufReader<fs::File>> {
 
 pub(crate) fn read_secure() -> io::Result<String> {
     let mut input = Input::buffered()?;
+    let fd = input.as_raw_fd();
+    read_secure_impl(fd, |rv| input.read_line(rv))
+}
 
-    let mut termios = mem::MaybeUninit::uninit();
-    c_result(|| unsafe { libc::tcgetattr(input.as_raw_fd(), termios.as_mut_ptr()) })?;
-    let mut termios = unsafe { termios.assume_init() };
-    let original = termios;
-    termios.c_lflag &= !libc::ECHO;
-    c_result(|| unsafe { libc::tcsetattr(input.as_raw_fd(), libc::TCSAFLUSH, &termios) })?;
+/// Reads a line without echo from the given reader.
+///
+/// The reader is read byte by byte so that nothing after the line is
+/// consumed.  Echo is only turned off if the reader is a terminal.
+pub(crate) fn read_secure_from<R: Read + AsRawFd + ?Sized>(read: &mut R) -> io::Result<String> {
+    let fd = read.as_raw_fd();
+    read_secure_impl(fd, |rv| {
+        let mut line = Vec::new();
+        let mut byte = [0];
+        while read.read(&mut byte)? == 1 {
+            line.push(byte[0]);
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        rv.push_str(
+            str::from_utf8(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+        );
+        Ok(line.len())
+    })
+}
+
+fn read_secure_impl<F>(fd: RawFd, read_line: F) -> io::Result<String>
+where
+    F: FnOnce(&mut String) -> io::Result<usize>,
+{
+    let original = if is_a_terminal(&fd) {
+        let mut termios = mem::MaybeUninit::uninit();
+        c_result(|| unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) })?;
+        let mut termios = unsafe { termios.assume_init() };
+        let original = termios;
+        termios.c_lflag &= !libc::ECHO;
+        c_result(|| unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &termios) })?;
+        Some(original)
+    } else {
+        None
+    };
     let mut rv = String::new();
 
-    let read_rv = input.read_line(&mut rv);
+    let read_rv = read_line(&mut rv);
 
-    c_result(|| unsafe { libc::tcsetattr(input.as_raw_fd(), libc::TCSAFLUSH, &original) })?;
+    if let Some(original) = original {
+        c_result(|| unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &original) })?;
+    }
 
     read_rv.map(|_| {
         let len = rv.trim_end_matches(&['\r', '\n'][..]).len();
@@ -151,22 +188,31 @@ pub(crate) fn read_secure() -> io::Result<String> {
     })
 }
 
-fn poll_fd(fd: RawFd, timeout: i32) -> io::Result<bool> {
-    let mut pollfd = libc::pollfd {
-        fd,
-        events: libc::POLLIN,
-        revents: 0,
-    };
-    let ret = unsafe { libc::poll(&mut pollfd as *mut _, 1, timeout) };
+/// Waits until one of `fds` is readable and returns the index of the
+/// first one that is, or `None` on timeout.
+fn poll_fds(fds: &[RawFd], timeout: i32) -> io::Result<Option<usize>> {
+    let mut pollfds: Vec<_> = fds
+        .iter()
+        .map(|&fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    let ret = unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as _, timeout) };
     if ret < 0 {
         Err(io::Error::last_os_error())
     } else {
-        Ok(pollfd.revents & libc::POLLIN != 0)
+        // a hangup is reported as ready so that the following read fails
+        // instead of waiting for input that never comes.
+        Ok(pollfds.iter().position(|pollfd| {
+            pollfd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0
+        }))
     }
 }
 
 #[cfg(target_os = "macos")]
-fn select_fd(fd: RawFd, timeout: i32) -> io::Result<bool> {
+fn select_fds(fds: &[RawFd], timeout: i32) -> io::Result<Option<usize>> {
     unsafe {
         let mut read_fd_set: libc::fd_set = mem::zeroed();
 
@@ -176,15 +222,17 @@ fn select_fd(fd: RawFd, timeout: i32) -> io::Result<bool> {
         } else {
             timeout_val = libc::timeval {
                 tv_sec: (timeout / 1000) as _,
-                tv_usec: (timeout * 1000) as _,
+                tv_usec: ((timeout % 1000) * 1000) as _,
             };
             &mut timeout_val
         };
 
         libc::FD_ZERO(&mut read_fd_set);
-        libc::FD_SET(fd, &mut read_fd_set);
+        for &fd in fds {
+            libc::FD_SET(fd, &mut read_fd_set);
+        }
         let ret = libc::select(
-            fd + 1,
+            fds.iter().max().map_or(0, |fd| fd + 1),
             &mut read_fd_set,
             ptr::null_mut(),
             ptr::null_mut(),
@@ -193,38 +241,44 @@ fn select_fd(fd: RawFd, timeout: i32) -> io::Result<bool> {
         if ret < 0 {
             Err(io::Error::last_os_error())
         } else {
-            Ok(libc::FD_ISSET(fd, &read_fd_set))
+            Ok(fds.iter().position(|&fd| libc::FD_ISSET(fd, &read_fd_set)))
         }
     }
 }
 
 fn select_or_poll_term_fd(fd: RawFd, timeout: i32) -> io::Result<bool> {
+    Ok(select_or_poll_term_fds(&[fd], timeout)?.is_some())
+}
+
+fn select_or_poll_term_fds(fds: &[RawFd], timeout: i32) -> io::Result<Option<usize>> {
+    // A signal such as SIGWINCH interrupts the wait without any input
+    // having arrived, so keep waiting for the rest of the timeout.
+    let deadline = (timeout > 0).then(|| Instant::now() + Duration::from_millis(timeout as u64));
+    let mut timeout = timeout;
+    loop {
+        match select_or_poll_term_fds_once(fds, timeout) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                if let Some(deadline) = deadline {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    timeout = remaining.as_millis() as i32;
+                }
+            }
+            rv => return rv,
+        }
+    }
+}
+
+fn select_or_poll_term_fds_once(fds: &[RawFd], timeout: i32) -> io::Result<Option<usize>> {
     // There is a bug on macos that ttys cannot be polled, only select()
     // works.  However given how problematic select is in general, we
     // normally want to use poll there too.
     #[cfg(target_os = "macos")]
     {
-        if unsafe { libc::isatty(fd) == 1 } {
-            return select_fd(fd, timeout);
+        if fds.iter().any(|&fd| unsafe { libc::isatty(fd) == 1 }) {
+            return select_fds(fds, timeout);
         }
     }
-    poll_fd(fd, timeout)
-}
-
-fn read_single_char(fd: RawFd) -> io::Result<Option<char>> {
-    // timeout of zero means that it will not block
-    let is_ready = select_or_poll_term_fd(fd, 0)?;
-
-    if is_ready {
-        // if there is something to be read, take 1 byte from it
-        let mut buf: [u8; 1] = [0];
-
-        read_bytes(fd, &mut buf, 1)?;
-        Ok(Some(buf[0] as char))
-    } else {
-        //there is nothing to be read
-        Ok(None)
-    }
+    poll_fds(fds, timeout)
 }
 
 // Similar to libc::read. Read count bytes into slice buf from descriptor fd.
@@ -239,145 +293,212 @@ fn read_bytes(fd: RawFd, buf: &mut [u8], count: u8) -> io::Result<u8> {
             io::ErrorKind::UnexpectedEof,
             "Reached end of file",
         ))
-    } else if buf[0] == b'\x03' {
-        Err(io::Error::new(
-            io::ErrorKind::Interrupted,
-            "read interrupted",
-        ))
     } else {
         Ok(read as u8)
     }
 }
 
-fn read_single_key_impl(fd: RawFd) -> Result<Key, io::Error> {
-    loop {
-        match read_single_char(fd)? {
-            Some('\x1b') => {
-                // Escape was read, keep reading in case we find a familiar key
-                break if let Some(c1) = read_single_char(fd)? {
-                    if c1 == '[' {
-                        if let Some(c2) = read_single_char(fd)? {
-                            match c2 {
-                                'A' => Ok(Key::ArrowUp),
-                                'B' => Ok(Key::ArrowDown),
-                                'C' => Ok(Key::ArrowRight),
-                                'D' => Ok(Key::ArrowLeft),
-                                'H' => Ok(Key::Home),
-                                'F' => Ok(Key::End),
-                                'Z' => Ok(Key::BackTab),
-                                _ => {
-                                    let c3 = read_single_char(fd)?;
-                                    if let Some(c3) = c3 {
-                                        if c3 == '~' {
-                                            match c2 {
-                                                '1' => Ok(Key::Home), // tmux
-                                                '2' => Ok(Key::Insert),
-                                                '3' => Ok(Key::Del),
-                                                '4' => Ok(Key::End), // tmux
-                                                '5' => Ok(Key::PageUp),
-                                                '6' => Ok(Key::PageDown),
-                                                '7' => Ok(Key::Home), // xrvt
-                                                '8' => Ok(Key::End),  // xrvt
-                                                _ => Ok(Key::UnknownEscSeq(vec![c1, c2, c3])),
-                                            }
-                                        } else {
-                                            Ok(Key::UnknownEscSeq(vec![c1, c2, c3]))
-                                        }
-                                    } else {
-                                        // \x1b[ and 1 more char
-                                        Ok(Key::UnknownEscSeq(vec![c1, c2]))
-                                    }
-                                }
-                            }
-                        } else {
-                            // \x1b[ and no more input
-                            Ok(Key::UnknownEscSeq(vec![c1]))
-                        }
-                    } else {
-                        // char after escape is not [
-                        Ok(Key::UnknownEscSeq(vec![c1]))
-                    }
-                } else {
-                    //nothing after escape
-                    Ok(Key::Escape)
-                };
-            }
-            Some(c) => {
-                let byte = c as u8;
-                let mut buf: [u8; 4] = [byte, 0, 0, 0];
-
-                break if byte & 224u8 == 192u8 {
-                    // a two byte unicode character
-                    read_bytes(fd, &mut buf[1..], 1)?;
-                    Ok(key_from_utf8(&buf[..2]))
-                } else if byte & 240u8 == 224u8 {
-                    // a three byte unicode character
-                    read_bytes(fd, &mut buf[1..], 2)?;
-                    Ok(key_from_utf8(&buf[..3]))
-                } else if byte & 248u8 == 240u8 {
-                    // a four byte unicode character
-                    read_bytes(fd, &mut buf[1..], 3)?;
-                    Ok(key_from_utf8(&buf[..4]))
-                } else {
-                    Ok(match c {
-                        '\n' | '\r' => Key::Enter,
-                        '\x7f' => Key::Backspace,
-                        '\t' => Key::Tab,
-                        '\x01' => Key::Home,      // Control-A (home)
-                        '\x05' => Key::End,       // Control-E (end)
-                        '\x08' => Key::Backspace, // Control-H (8) (Identical to '\b')
-                        _ => Key::Char(c),
-                    })
-                };
-            }
-            None => {
-                // there is no subsequent byte ready to be read, block and wait for input
-                // negative timeout means that it will block indefinitely
-                match select_or_poll_term_fd(fd, -1) {
-                    Ok(_) => continue,
-                    Err(_) => break Err(io::Error::last_os_error()),
-                }
-            }
-        }
-    }
-}
-
-pub(crate) fn read_single_key(ctrlc_key: bool) -> io::Result<Key> {
-    let input = Input::unbuffered()?;
+/// How long to wait for the rest of an escape sequence after `ESC [` or
+/// `ESC O`, or for the rest of a paste, in milliseconds.
+const SEQUENCE_TIMEOUT_MS: i32 = 500;
 
+/// Puts the terminal `fd` into raw mode and returns its original mode.
+///
+/// Output processing is kept so that written line breaks still return
+/// the cursor.
+fn enter_raw_mode(fd: RawFd) -> io::Result<libc::termios> {
     let mut termios = core::mem::MaybeUninit::uninit();
-    c_result(|| unsafe { libc::tcgetattr(input.as_raw_fd(), termios.as_mut_ptr()) })?;
+    c_result(|| unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) })?;
     let mut termios = unsafe { termios.assume_init() };
     let original = termios;
     make_raw(&mut termios);
     termios.c_oflag = original.c_oflag;
-    c_result(|| unsafe { libc::tcsetattr(input.as_raw_fd(), libc::TCSADRAIN, &termios) })?;
-    let rv = read_single_key_impl(input.as_raw_fd());
-    c_result(|| unsafe { libc::tcsetattr(input.as_raw_fd(), libc::TCSADRAIN, &original) })?;
-
-    // if the user hit ^C we want to signal SIGINT to ourselves.
-    if let Err(ref err) = rv {
-        if err.kind() == io::ErrorKind::Interrupted {
-            if !ctrlc_key {
-                unsafe {
-                    libc::raise(libc::SIGINT);
+    c_result(|| unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, &termios) })?;
+    Ok(original)
+}
+
+fn restore_mode(fd: RawFd, original: &libc::termios) -> io::Result<()> {
+    c_result(|| unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, original) })
+}
+
+/// A terminal kept in raw mode between key reads.
+struct RawMode {
+    fd: RawFd,
+    /// Keeps `/dev/tty` open if that is where keys are read from.
+    _input: Option<Input<fs::File>>,
+    original: libc::termios,
+}
+
+/// The state of key reads on a terminal that outlives a single read.
+///
+/// A read that times out in the middle of an escape sequence or paste
+/// leaves it in the decoder for the next read.  While raw mode is held
+/// with [`enter_raw_mode`](Self::enter_raw_mode) the terminal is not
+/// switched back and forth for every read, so nothing typed between two
+/// reads is echoed or held back until the next line.
+#[derive(Default)]
+pub(crate) struct KeyReader {
+    decoder: KeyDecoder,
+    /// When the last byte of a pending sequence or paste was read.
+    last_byte: Option<Instant>,
+    raw: Option<RawMode>,
+}
+
+impl core::fmt::Debug for KeyReader {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyReader")
+            .field("decoder", &self.decoder)
+            .field("last_byte", &self.last_byte)
+            .field("raw", &self.raw.as_ref().map(|raw| raw.fd))
+            .finish()
+    }
+}
+
+impl KeyReader {
+    /// Keeps the terminal at `fd`, or the one of the process if `None`,
+    /// in raw mode until [`leave_raw_mode`](Self::leave_raw_mode).
+    ///
+    /// Does nothing if `fd` is not a terminal.
+    pub(crate) fn enter_raw_mode(&mut self, fd: Option<RawFd>) -> io::Result<()> {
+        self.leave_raw_mode()?;
+        let (fd, input) = match fd {
+            Some(fd) => (fd, None),
+            None => {
+                let input = Input::unbuffered()?;
+                (input.as_raw_fd(), Some(input))
+            }
+        };
+        if is_a_terminal(&fd) {
+            let original = enter_raw_mode(fd)?;
+            self.raw = Some(RawMode {
+                fd,
+                _input: input,
+                original,
+            });
+        }
+        Ok(())
+    }
+
+    /// Restores the mode the terminal had before raw mode was entered.
+    pub(crate) fn leave_raw_mode(&mut self) -> io::Result<()> {
+        match self.raw.take() {
+            Some(raw) => restore_mode(raw.fd, &raw.original),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads a single key from `fd`, or from the terminal of the process
+    /// if `None`.
+    ///
+    /// A terminal is put into raw mode while waiting and reading, so that
+    /// a key is seen as soon as it is pressed.  Returns the key both with
+    /// its modifiers and as a plain key, or `None` if no key was completed
+    /// within the timeout or `wake` became readable first.
+    pub(crate) fn read_key(
+        &mut self,
+        fd: Option<RawFd>,
+        ctrlc_key: bool,
+        timeout: Option<Duration>,
+        wake: Option<RawFd>,
+    ) -> io::Result<Option<(KeyEvent, Key)>> {
+        let input;
+        let fd = match fd.or(self.raw.as_ref().map(|raw| raw.fd)) {
+            Some(fd) => fd,
+            None => {
+                input = Input::unbuffered()?;
+                input.as_raw_fd()
+            }
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let rv = if self.raw.as_ref().is_some_and(|raw| raw.fd == fd) || !is_a_terminal(&fd) {
+            self.read_key_impl(fd, deadline, wake)
+        } else {
+            let original = enter_raw_mode(fd)?;
+            let rv = self.read_key_impl(fd, deadline, wake);
+            restore_mode(fd, &original)?;
+            rv
+        };
+
+        // if the user hit ^C we want to signal SIGINT to ourselves.
+        if let Err(ref err) = rv {
+            if err.kind() == io::ErrorKind::Interrupted {
+                if !ctrlc_key {
+                    unsafe {
+                        libc::raise(libc::SIGINT);
+                    }
+                } else {
+                    return Ok(Some((KeyEvent::from(Key::CtrlC), Key::CtrlC)));
                 }
-            } else {
-                return Ok(Key::CtrlC);
             }
         }
+
+        rv
     }
 
-    rv
-}
+    fn read_key_impl(
+        &mut self,
+        fd: RawFd,
+        deadline: Option<Instant>,
+        wake: Option<RawFd>,
+    ) -> io::Result<Option<(KeyEvent, Key)>> {
+        loop {
+            let remaining = deadline.map_or(-1, |deadline| {
+                // round up so that short timeouts still wait
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let millis = (remaining.as_micros() + 999) / 1000;
+                millis.min(i32::MAX as u128) as i32
+            });
+            if self.decoder.is_escape_pending() || self.decoder.is_paste_pending() {
+                // A lone escape looks like the start of a sequence, so only
+                // use what is already there to tell them apart.  The rest of
+                // a started sequence or paste is on its way but might arrive
+                // in a later read, a paste that stalls lost its end.
+                let wait = if self.decoder.is_lone_escape() {
+                    0
+                } else {
+                    let waited = self.last_byte.map_or(0, |last_byte| {
+                        last_byte.elapsed().as_millis().min(i32::MAX as u128) as i32
+                    });
+                    SEQUENCE_TIMEOUT_MS.saturating_sub(waited).max(0)
+                };
+                if remaining >= 0 && remaining < wait {
+                    // keep the sequence for the next read
+                    if !select_or_poll_term_fd(fd, remaining)? {
+                        return Ok(None);
+                    }
+                } else if !select_or_poll_term_fd(fd, wait)? {
+                    if let Some(key) = self.decoder.decode_timeout() {
+                        self.last_byte = None;
+                        return Ok(Some(key));
+                    }
+                }
+            } else if let Some(wake) = wake {
+                // input wins if both are ready, so that no key is lost
+                if select_or_poll_term_fds(&[fd, wake], remaining)? != Some(0) {
+                    return Ok(None);
+                }
+            } else if remaining >= 0 && !select_or_poll_term_fd(fd, remaining)? {
+                return Ok(None);
+            }
 
-fn key_from_utf8(buf: &[u8]) -> Key {
-    if let Ok(s) = str::from_utf8(buf) {
-        if let Some(c) = s.chars().next() {
-            return Key::Char(c);
+            let mut buf = [0];
+            read_bytes(fd, &mut buf, 1)?;
+            // pasted text may contain anything, including ^C
+            if buf[0] == b'\x03' && !self.decoder.is_paste_pending() {
+                self.decoder = KeyDecoder::new();
+                self.last_byte = None;
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "read interrupted",
+                ));
+            }
+            if let Some(key) = self.decoder.decode(buf[0]) {
+                self.last_byte = None;
+                return Ok(Some(key));
+            }
+            self.last_byte = Some(Instant::now());
         }
     }
-    Key::Unknown
 }
 
 #[cfg(target_os = "macos")]
@@ -395,6 +516,224 @@ pub(crate) fn wants_emoji() -> bool {
     })
 }
 
+/// Write ends of the pipes that are notified about a `SIGWINCH`, or -1
+/// for a free slot.
+///
+/// The signal handler may not allocate or lock, so the listeners live in
+/// a fixed table of atomics.  The pipes are never closed as the handler
+/// may still write to a pipe after its slot was freed.
+static RESIZE_PIPES: [AtomicI32; 8] = [
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+    AtomicI32::new(-1),
+];
+
+/// The number of resizes since the handler was installed.
+static RESIZE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The `SIGWINCH` handler that was installed before ours.
+static PREVIOUS_RESIZE_HANDLER: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+static PREVIOUS_RESIZE_FLAGS: AtomicI32 = AtomicI32::new(0);
+
+type SignalHandler = extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void);
+
+extern "C" fn handle_resize(
+    signal: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    // the interrupted code must not see errno change under its feet
+    let errno = errno_location();
+    let saved = errno.map(|errno| unsafe { *errno });
+    notify_resize(signal, info, context);
+    if let (Some(errno), Some(saved)) = (errno, saved) {
+        unsafe { *errno = saved };
+    }
+}
+
+fn notify_resize(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    RESIZE_COUNT.fetch_add(1, Ordering::Release);
+    for pipe in &RESIZE_PIPES {
+        let fd = pipe.load(Ordering::Acquire);
+        if fd >= 0 {
+            // the pipe is non-blocking, if it is full a wakeup is pending anyway
+            unsafe { libc::write(fd, b"\0".as_ptr().cast(), 1) };
+        }
+    }
+
+    // keep handlers of the application or other libraries working
+    let previous = PREVIOUS_RESIZE_HANDLER.load(Ordering::Relaxed);
+    if previous == libc::SIG_DFL || previous == libc::SIG_IGN {
+        return;
+    }
+    unsafe {
+        if PREVIOUS_RESIZE_FLAGS.load(Ordering::Relaxed) & libc::SA_SIGINFO != 0 {
+            let handler: SignalHandler = mem::transmute(previous);
+            handler(signal, info, context);
+        } else {
+            let handler: extern "C" fn(libc::c_int) = mem::transmute(previous);
+            handler(signal);
+        }
+    }
+}
+
+/// Returns the location of `errno` for the calling thread.
+#[cfg(any(target_os = "linux", target_os = "emscripten"))]
+fn errno_location() -> Option<*mut libc::c_int> {
+    Some(unsafe { libc::__errno_location() })
+}
+
+#[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+fn errno_location() -> Option<*mut libc::c_int> {
+    Some(unsafe { libc::__errno() })
+}
+
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+fn errno_location() -> Option<*mut libc::c_int> {
+    Some(unsafe { libc::__error() })
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+fn errno_location() -> Option<*mut libc::c_int> {
+    Some(unsafe { libc::___errno() })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "emscripten",
+    target_os = "android",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "solaris",
+    target_os = "illumos",
+)))]
+fn errno_location() -> Option<*mut libc::c_int> {
+    None
+}
+
+fn install_resize_handler() -> io::Result<()> {
+    static INSTALLED: OnceLock<Option<i32>> = OnceLock::new();
+
+    let error = INSTALLED.get_or_init(|| unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handle_resize as SignalHandler as usize;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = mem::zeroed();
+        if libc::sigaction(libc::SIGWINCH, &action, &mut previous) < 0 {
+            return io::Error::last_os_error().raw_os_error();
+        }
+        PREVIOUS_RESIZE_HANDLER.store(previous.sa_sigaction, Ordering::Relaxed);
+        PREVIOUS_RESIZE_FLAGS.store(previous.sa_flags, Ordering::Relaxed);
+        None
+    });
+    match *error {
+        Some(code) => Err(io::Error::from_raw_os_error(code)),
+        None => Ok(()),
+    }
+}
+
+/// The pipes of the slots in [`RESIZE_PIPES`] as `(read, write)`, created
+/// when a slot is first used.
+static SLOT_PIPES: Mutex<[Option<(RawFd, RawFd)>; 8]> = Mutex::new([None; 8]);
+
+/// Returns the number of resizes so far.
+///
+/// This only counts once a [`ResizeWatcher`] was created.
+pub(crate) fn resize_count() -> usize {
+    RESIZE_COUNT.load(Ordering::Acquire)
+}
+
+/// A self-pipe that becomes readable when the terminal window is resized.
+///
+/// Creating the first one installs a `SIGWINCH` handler that chains to
+/// the previously installed handler.  Pipes are reused by later watchers
+/// without draining them first, so that creating a watcher only takes a
+/// free slot.  Notifications from before are told apart by the resize
+/// count.
+#[derive(Debug)]
+pub(crate) struct ResizeWatcher {
+    read: RawFd,
+    slot: usize,
+    /// The resize count that was last reported.
+    seen: AtomicUsize,
+}
+
+impl ResizeWatcher {
+    pub(crate) fn new() -> io::Result<Self> {
+        install_resize_handler()?;
+        let mut pipes = SLOT_PIPES.lock().unwrap();
+        let slot = RESIZE_PIPES
+            .iter()
+            .position(|pipe| pipe.load(Ordering::Acquire) < 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "too many resize listeners"))?;
+        let (read, write) = match pipes[slot] {
+            Some(pipe) => pipe,
+            None => {
+                let mut fds = [0; 2];
+                c_result(|| unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+                for fd in fds {
+                    unsafe {
+                        libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+                        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+                    }
+                }
+                *pipes[slot].insert((fds[0], fds[1]))
+            }
+        };
+        RESIZE_PIPES[slot].store(write, Ordering::Release);
+        Ok(Self {
+            read,
+            slot,
+            seen: AtomicUsize::new(resize_count()),
+        })
+    }
+
+    /// Waits up to `timeout` milliseconds for a resize, -1 waits forever.
+    ///
+    /// Returns `true` if the window was resized.  Resizes that happened
+    /// since the last call are reported right away.
+    pub(crate) fn wait(&self, timeout: i32) -> io::Result<bool> {
+        if self.drain() {
+            return Ok(true);
+        }
+        if !select_or_poll_term_fd(self.read, timeout)? {
+            return Ok(false);
+        }
+        Ok(self.drain())
+    }
+
+    /// Consumes pending resize notifications and returns whether the
+    /// window was resized since the last call.
+    pub(crate) fn drain(&self) -> bool {
+        let mut buf = [0u8; 64];
+        while unsafe { libc::read(self.read, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+        // the count goes up before the pipe is written to, so a resize
+        // that is not counted yet is reported by the next call
+        let count = resize_count();
+        count != self.seen.swap(count, Ordering::Relaxed)
+    }
+}
+
+impl AsRawFd for ResizeWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.read
+    }
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        RESIZE_PIPES[self.slot].store(-1, Ordering::Release);
+    }
+}
+
 pub(crate) fn set_title<T: Display>(title: T) {
     print!("\x1b]0;{title}\x07");
 }
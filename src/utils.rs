@@ -1,16 +1,20 @@
 use alloc::borrow::Cow;
 use core::{
     fmt::{self, Debug, Formatter},
+    iter, mem,
+    ops::{Range, RangeInclusive},
     sync::atomic::{AtomicBool, Ordering},
 };
 use std::env;
 
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
 
 use crate::term::{wants_emoji, Term};
 
 #[cfg(feature = "ansi-parsing")]
-use crate::ansi::AnsiCodeIterator;
+use crate::ansi::{AnsiCodeIterator, AnsiToken, AnsiTokens, CsiParams};
+#[cfg(feature = "ansi-parsing")]
+use core::iter::FusedIterator;
 
 fn default_colors_enabled(out: &Term) -> bool {
     (out.features().colors_supported()
@@ -112,19 +116,121 @@ pub fn set_true_colors_enabled_stderr(val: bool) {
 }
 
 /// Measure the width of a string in terminal characters.
+///
+/// Characters are measured according to the [`WidthPolicy`] of the process.
 pub fn measure_text_width(s: &str) -> usize {
+    text_width(s, &current_width_policy())
+}
+
+/// Measures the width of a string like [`measure_text_width`] with the given
+/// policy.
+fn text_width(s: &str, policy: &WidthPolicy) -> usize {
     #[cfg(feature = "ansi-parsing")]
     {
-        AnsiCodeIterator::new(s)
-            .filter_map(|(s, is_ansi)| match is_ansi {
-                false => Some(str_width(s)),
-                true => None,
-            })
-            .sum()
+        // Text and control characters between two escapes are measured as
+        // one run, so that for instance `\r\n` is a single grapheme.
+        let mut width = 0;
+        let (mut start, mut end) = (0, 0);
+        for token in AnsiTokens::new(s) {
+            let escape = match token {
+                AnsiToken::Text(text) => {
+                    end += text.len();
+                    continue;
+                }
+                AnsiToken::Control(c) => {
+                    end += c.len_utf8();
+                    continue;
+                }
+                AnsiToken::Esc(esc) => esc.as_str(),
+                AnsiToken::Csi(csi) => csi.as_str(),
+                AnsiToken::Osc(osc) => osc.as_str(),
+                AnsiToken::ControlString(string) => string.as_str(),
+            };
+            width += policy.str_width(&s[start..end]);
+            start = end + escape.len();
+            end = start;
+        }
+        width + policy.str_width(&s[start..end])
     }
     #[cfg(not(feature = "ansi-parsing"))]
     {
-        str_width(s)
+        policy.str_width(s)
+    }
+}
+
+/// Measure the width of a string like a terminal would display it.
+///
+/// Unlike [`measure_text_width`] this follows the cursor movement of control
+/// characters: tabs advance to the next multiple of `tab_width`, backspace
+/// moves back one column, and carriage returns and newlines go back to the
+/// first column.  Other control characters take no space.  The result is
+/// the widest column that any line reaches.
+///
+/// ```
+/// # use console::measure_text_width_with;
+/// assert_eq!(measure_text_width_with("ab\tc", 4), 5);
+/// assert_eq!(measure_text_width_with("abc\rd", 4), 3);
+/// assert_eq!(measure_text_width_with("ab\x08", 4), 2);
+/// ```
+pub fn measure_text_width_with(s: &str, tab_width: usize) -> usize {
+    let policy = current_width_policy();
+    let mut col = 0;
+    let mut width = 0;
+    for (item, is_ansi) in ansi_items(s) {
+        if is_ansi {
+            continue;
+        }
+        for g in graphemes(item) {
+            col = advance_column(col, g, tab_width, &policy);
+            width = width.max(col);
+        }
+    }
+    width
+}
+
+/// Replaces tabs with spaces up to the next multiple of `tab_width`.
+///
+/// Columns are counted like in [`measure_text_width_with`] and escape codes
+/// are kept as they are.  With a `tab_width` of zero tabs are removed.
+///
+/// ```
+/// # use console::expand_tabs;
+/// assert_eq!(expand_tabs("a\tbc\td", 4), "a   bc  d");
+/// ```
+pub fn expand_tabs(s: &str, tab_width: usize) -> Cow<'_, str> {
+    if !s.contains('\t') {
+        return Cow::Borrowed(s);
+    }
+
+    let policy = current_width_policy();
+    let mut rv = String::with_capacity(s.len());
+    let mut col = 0;
+    for (item, is_ansi) in ansi_items(s) {
+        if is_ansi {
+            rv.push_str(item);
+            continue;
+        }
+        for g in graphemes(item) {
+            let next = advance_column(col, g, tab_width, &policy);
+            if g == "\t" {
+                rv.extend(iter::repeat(' ').take(next - col));
+            } else {
+                rv.push_str(g);
+            }
+            col = next;
+        }
+    }
+    Cow::Owned(rv)
+}
+
+/// Returns the cursor column after printing a grapheme cluster at `col`.
+fn advance_column(col: usize, g: &str, tab_width: usize, policy: &WidthPolicy) -> usize {
+    match g {
+        "\t" if tab_width > 0 => (col / tab_width + 1) * tab_width,
+        "\r" | "\n" | "\r\n" => 0,
+        "\x08" => col.saturating_sub(1),
+        _ if g.starts_with(char::is_control) => col,
+        _ => col + policy.grapheme_width(g),
     }
 }
 
@@ -168,6 +274,69 @@ impl Color {
             _ => false,
         }
     }
+
+    /// Turns a basic color into the matching 256 color.
+    #[cfg(feature = "ansi-parsing")]
+    fn to_color256(self, bright: bool) -> Color {
+        match self {
+            Color::Color256(_) | Color::TrueColor(..) => self,
+            _ if bright => Color::Color256(self.ansi_num() as u8 + 8),
+            _ => Color::Color256(self.ansi_num() as u8),
+        }
+    }
+
+    /// Returns the RGB value of the color in the default xterm palette.
+    #[cfg(feature = "ansi-parsing")]
+    pub(crate) fn rgb(self) -> (u8, u8, u8) {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0x00, 0x00, 0x00),
+            (0xcd, 0x00, 0x00),
+            (0x00, 0xcd, 0x00),
+            (0xcd, 0xcd, 0x00),
+            (0x00, 0x00, 0xee),
+            (0xcd, 0x00, 0xcd),
+            (0x00, 0xcd, 0xcd),
+            (0xe5, 0xe5, 0xe5),
+            (0x7f, 0x7f, 0x7f),
+            (0xff, 0x00, 0x00),
+            (0x00, 0xff, 0x00),
+            (0xff, 0xff, 0x00),
+            (0x5c, 0x5c, 0xff),
+            (0xff, 0x00, 0xff),
+            (0x00, 0xff, 0xff),
+            (0xff, 0xff, 0xff),
+        ];
+        const LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+        match self {
+            Color::TrueColor(r, g, b) => (r, g, b),
+            Color::Color256(n @ 0..=15) => BASIC[n as usize],
+            Color::Color256(n @ 16..=231) => {
+                let n = (n - 16) as usize;
+                (LEVELS[n / 36], LEVELS[n / 6 % 6], LEVELS[n % 6])
+            }
+            Color::Color256(n) => {
+                let level = 8 + 10 * (n - 232);
+                (level, level, level)
+            }
+            _ => BASIC[self.ansi_num()],
+        }
+    }
+
+    #[cfg(feature = "ansi-parsing")]
+    #[inline]
+    fn from_ansi_num(num: u16) -> Color {
+        match num {
+            0 => Color::Black,
+            1 => Color::Red,
+            2 => Color::Green,
+            3 => Color::Yellow,
+            4 => Color::Blue,
+            5 => Color::Magenta,
+            6 => Color::Cyan,
+            _ => Color::White,
+        }
+    }
 }
 
 /// A terminal style attribute.
@@ -218,6 +387,15 @@ impl Attributes {
         self
     }
 
+    #[cfg(feature = "ansi-parsing")]
+    #[inline]
+    #[must_use]
+    const fn remove(mut self, attr: Attribute) -> Self {
+        let bit = attr as u16;
+        self.0 &= !(1 << bit);
+        self
+    }
+
     #[inline]
     const fn bits(self) -> BitsIter {
         BitsIter(self.0)
@@ -272,6 +450,18 @@ pub enum Alignment {
     Right,
 }
 
+/// Defines which part of a string is removed by truncation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TruncateMode {
+    /// Keeps the start of the string and elides the end.
+    #[default]
+    End,
+    /// Keeps the end of the string and elides the start.
+    Start,
+    /// Keeps the start and the end of the string and elides the middle.
+    Middle,
+}
+
 /// A stored style that can be applied.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Style {
@@ -774,6 +964,43 @@ impl<D> StyledObject<D> {
     }
 }
 
+impl Style {
+    /// Writes the escape codes that turn on this style and returns `true` if
+    /// anything was written.
+    fn write_prefix<W: fmt::Write>(&self, f: &mut W) -> Result<bool, fmt::Error> {
+        let mut written = false;
+        if let Some(fg) = self.fg {
+            if let Color::TrueColor(r, g, b) = fg {
+                write!(f, "\x1b[38;2;{};{};{}m", r, g, b)?;
+            } else if fg.is_color256() {
+                write!(f, "\x1b[38;5;{}m", fg.ansi_num())?;
+            } else if self.fg_bright {
+                write!(f, "\x1b[38;5;{}m", fg.ansi_num() + 8)?;
+            } else {
+                write!(f, "\x1b[{}m", fg.ansi_num() + 30)?;
+            }
+            written = true;
+        }
+        if let Some(bg) = self.bg {
+            if let Color::TrueColor(r, g, b) = bg {
+                write!(f, "\x1b[48;2;{};{};{}m", r, g, b)?;
+            } else if bg.is_color256() {
+                write!(f, "\x1b[48;5;{}m", bg.ansi_num())?;
+            } else if self.bg_bright {
+                write!(f, "\x1b[48;5;{}m", bg.ansi_num() + 8)?;
+            } else {
+                write!(f, "\x1b[{}m", bg.ansi_num() + 40)?;
+            }
+            written = true;
+        }
+        if !self.attrs.is_empty() {
+            write!(f, "{}", self.attrs)?;
+            written = true;
+        }
+        Ok(written)
+    }
+}
+
 macro_rules! impl_fmt {
     ($name:ident) => {
         impl<D: fmt::$name> fmt::$name for StyledObject<D> {
@@ -787,34 +1014,7 @@ macro_rules! impl_fmt {
                         false => colors_enabled(),
                     })
                 {
-                    if let Some(fg) = self.style.fg {
-                        if let Color::TrueColor(r, g, b) = fg {
-                            write!(f, "\x1b[38;2;{};{};{}m", r, g, b)?;
-                        } else if fg.is_color256() {
-                            write!(f, "\x1b[38;5;{}m", fg.ansi_num())?;
-                        } else if self.style.fg_bright {
-                            write!(f, "\x1b[38;5;{}m", fg.ansi_num() + 8)?;
-                        } else {
-                            write!(f, "\x1b[{}m", fg.ansi_num() + 30)?;
-                        }
-                        reset = true;
-                    }
-                    if let Some(bg) = self.style.bg {
-                        if let Color::TrueColor(r, g, b) = bg {
-                            write!(f, "\x1b[48;2;{};{};{}m", r, g, b)?;
-                        } else if bg.is_color256() {
-                            write!(f, "\x1b[48;5;{}m", bg.ansi_num())?;
-                        } else if self.style.bg_bright {
-                            write!(f, "\x1b[48;5;{}m", bg.ansi_num() + 8)?;
-                        } else {
-                            write!(f, "\x1b[{}m", bg.ansi_num() + 40)?;
-                        }
-                        reset = true;
-                    }
-                    if !self.style.attrs.is_empty() {
-                        write!(f, "{}", self.style.attrs)?;
-                        reset = true;
-                    }
+                    reset = self.style.write_prefix(f)?;
                 }
                 fmt::$name::fmt(&self.val, f)?;
                 if reset {
@@ -836,6 +1036,180 @@ impl_fmt!(Pointer);
 impl_fmt!(UpperExp);
 impl_fmt!(UpperHex);
 
+#[cfg(feature = "ansi-parsing")]
+impl Style {
+    /// Returns the foreground color as a 256 color or a true color.
+    pub(crate) fn fg_color(&self) -> Option<Color> {
+        self.fg.map(|color| color.to_color256(self.fg_bright))
+    }
+
+    /// Returns the background color as a 256 color or a true color.
+    pub(crate) fn bg_color(&self) -> Option<Color> {
+        self.bg.map(|color| color.to_color256(self.bg_bright))
+    }
+
+    /// Returns `true` if the style has the given attribute.
+    pub(crate) fn has_attr(&self, attr: Attribute) -> bool {
+        self.attrs.attrs().any(|a| a == attr)
+    }
+
+    /// Applies an escape code to the style if it is an SGR sequence.
+    pub(crate) fn apply_escape(self, escape: &str) -> Self {
+        match AnsiToken::from_escape(escape) {
+            AnsiToken::Csi(csi)
+                if csi.final_char == 'm'
+                    && csi.intermediates.is_empty()
+                    && csi.params.private_marker().is_none() =>
+            {
+                self.apply_sgr(csi.params)
+            }
+            _ => self,
+        }
+    }
+
+    /// Applies the parameters of an SGR sequence (`ESC [ ... m`) to the style.
+    fn apply_sgr(mut self, params: CsiParams) -> Self {
+        let mut params = params.iter().map(|param| param.unwrap_or(0)).peekable();
+        if params.peek().is_none() {
+            return self.reset_sgr();
+        }
+
+        while let Some(param) = params.next() {
+            self = match param {
+                0 => self.reset_sgr(),
+                1..=9 => self.attr(Attribute::MAP[param as usize - 1]),
+                22 => {
+                    self.attrs = self.attrs.remove(Attribute::Bold).remove(Attribute::Dim);
+                    self
+                }
+                23 | 24 | 27 | 28 | 29 => {
+                    self.attrs = self.attrs.remove(Attribute::MAP[param as usize - 21]);
+                    self
+                }
+                25 => {
+                    self.attrs = self
+                        .attrs
+                        .remove(Attribute::Blink)
+                        .remove(Attribute::BlinkFast);
+                    self
+                }
+                30..=37 => {
+                    self.fg_bright = false;
+                    self.fg(Color::from_ansi_num(param - 30))
+                }
+                90..=97 => self.fg(Color::from_ansi_num(param - 90)).bright(),
+                38 => match parse_sgr_color(&mut params) {
+                    Some(color) => {
+                        self.fg_bright = false;
+                        self.fg(color)
+                    }
+                    None => break,
+                },
+                39 => {
+                    self.fg = None;
+                    self.fg_bright = false;
+                    self
+                }
+                40..=47 => {
+                    self.bg_bright = false;
+                    self.bg(Color::from_ansi_num(param - 40))
+                }
+                100..=107 => self.bg(Color::from_ansi_num(param - 100)).on_bright(),
+                48 => match parse_sgr_color(&mut params) {
+                    Some(color) => {
+                        self.bg_bright = false;
+                        self.bg(color)
+                    }
+                    None => break,
+                },
+                49 => {
+                    self.bg = None;
+                    self.bg_bright = false;
+                    self
+                }
+                _ => self,
+            };
+        }
+        self
+    }
+
+    /// Resets everything but the output settings of the style.
+    fn reset_sgr(self) -> Self {
+        Style {
+            force: self.force,
+            for_stderr: self.for_stderr,
+            ..Style::new()
+        }
+    }
+}
+
+/// Parses the arguments of an extended color (`38` or `48`) SGR parameter.
+#[cfg(feature = "ansi-parsing")]
+fn parse_sgr_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let mut next_u8 = || params.next().and_then(|param| u8::try_from(param).ok());
+    match next_u8()? {
+        5 => Some(Color::Color256(next_u8()?)),
+        2 => Some(Color::TrueColor(next_u8()?, next_u8()?, next_u8()?)),
+        _ => None,
+    }
+}
+
+/// An iterator over the styled spans of a string containing ansi codes.
+///
+/// This decodes the SGR sequences in already colored text (for instance
+/// the output of a child process) and yields tuples in the form
+/// `(style, s)` where `s` is a slice of text without any ansi codes and
+/// `style` is the style that was active for it.  Escape codes other than
+/// SGR sequences are skipped.
+///
+/// ```
+/// # use console::{Style, StyledSpans};
+/// let spans: Vec<_> = StyledSpans::new("\x1b[1;31mError:\x1b[0m oops").collect();
+/// assert_eq!(spans, [
+///     (Style::new().red().bold(), "Error:"),
+///     (Style::new(), " oops"),
+/// ]);
+/// ```
+#[cfg(feature = "ansi-parsing")]
+#[derive(Debug, Clone)]
+pub struct StyledSpans<'a> {
+    iter: AnsiCodeIterator<'a>,
+    style: Style,
+}
+
+#[cfg(feature = "ansi-parsing")]
+impl<'a> StyledSpans<'a> {
+    /// Creates a new styled span iterator.
+    pub fn new(s: &'a str) -> StyledSpans<'a> {
+        StyledSpans {
+            iter: AnsiCodeIterator::new(s),
+            style: Style::new(),
+        }
+    }
+
+    /// Returns the style active at the current position.
+    pub fn current_style(&self) -> &Style {
+        &self.style
+    }
+}
+
+#[cfg(feature = "ansi-parsing")]
+impl<'a> Iterator for StyledSpans<'a> {
+    type Item = (Style, &'a str);
+
+    fn next(&mut self) -> Option<(Style, &'a str)> {
+        loop {
+            match self.iter.next()? {
+                (s, false) => return Some((self.style.clone(), s)),
+                (s, true) => self.style = self.style.clone().apply_escape(s),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ansi-parsing")]
+impl FusedIterator for StyledSpans<'_> {}
+
 /// "Intelligent" emoji formatter.
 ///
 /// This struct intelligently wraps an emoji so that it is rendered
@@ -867,37 +1241,199 @@ impl fmt::Display for Emoji<'_, '_> {
     }
 }
 
-fn str_width(s: &str) -> usize {
-    #[cfg(feature = "unicode-width")]
-    {
-        use unicode_width::UnicodeWidthStr;
-        s.width()
+/// Defines how characters with an ambiguous East Asian width are measured.
+///
+/// Characters like `±`, `○` or box drawing characters are rendered one
+/// column wide by most terminals, but two columns wide by terminals that are
+/// set up for CJK text.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    /// Ambiguous characters take one column.
+    #[default]
+    Narrow,
+    /// Ambiguous characters take two columns.
+    Wide,
+}
+
+/// Defines how the width of characters is measured.
+///
+/// The policy in effect is set for the entire process with
+/// [`set_width_policy`] and used by [`measure_text_width`], [`truncate_str`],
+/// [`pad_str_with`] and all other functions that measure text.  Ambiguous
+/// widths are only taken into account with the `unicode-width` feature.
+///
+/// ```
+/// # use console::{AmbiguousWidth, WidthPolicy};
+/// let policy = WidthPolicy::new()
+///     .ambiguous(AmbiguousWidth::Wide)
+///     .override_width('\u{2500}'..='\u{257f}', 1);
+/// assert_eq!(policy.char_width('─'), 1);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WidthPolicy {
+    ambiguous: AmbiguousWidth,
+    overrides: Vec<(RangeInclusive<char>, usize)>,
+}
+
+impl WidthPolicy {
+    /// Creates a policy that measures ambiguous characters as narrow.
+    pub const fn new() -> Self {
+        Self {
+            ambiguous: AmbiguousWidth::Narrow,
+            overrides: Vec::new(),
+        }
     }
-    #[cfg(not(feature = "unicode-width"))]
-    {
-        s.chars().count()
+
+    /// Creates a policy from the locale of the environment.
+    ///
+    /// The first locale set in `LC_ALL`, `LC_CTYPE` or `LANG` is used.
+    /// Chinese, Japanese and Korean locales measure ambiguous characters as
+    /// wide.  This is the default policy of the process.
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Self::new().ambiguous(ambiguous_width_for_locale(&locale))
+    }
+
+    /// Sets how ambiguous characters are measured.
+    pub fn ambiguous(mut self, width: AmbiguousWidth) -> Self {
+        self.ambiguous = width;
+        self
+    }
+
+    /// Overrides the width of a range of characters.
+    ///
+    /// Overrides take precedence over all other rules, later overrides
+    /// take precedence over earlier ones.
+    pub fn override_width(mut self, chars: RangeInclusive<char>, width: usize) -> Self {
+        self.overrides.push((chars, width));
+        self
+    }
+
+    /// Returns how ambiguous characters are measured.
+    pub fn ambiguous_width(&self) -> AmbiguousWidth {
+        self.ambiguous
+    }
+
+    /// Returns the width of a single character under this policy.
+    pub fn char_width(&self, c: char) -> usize {
+        if let Some((_, width)) = self.overrides.iter().rev().find(|(r, _)| r.contains(&c)) {
+            return *width;
+        }
+        #[cfg(feature = "unicode-width")]
+        {
+            use unicode_width::UnicodeWidthChar;
+            match self.ambiguous {
+                AmbiguousWidth::Narrow => c.width(),
+                AmbiguousWidth::Wide => c.width_cjk(),
+            }
+            .unwrap_or(0)
+        }
+        #[cfg(not(feature = "unicode-width"))]
+        {
+            1
+        }
+    }
+
+    fn str_width(&self, s: &str) -> usize {
+        #[cfg(not(feature = "unicode-segmentation"))]
+        if self.overrides.is_empty() {
+            #[cfg(feature = "unicode-width")]
+            {
+                use unicode_width::UnicodeWidthStr;
+                return match self.ambiguous {
+                    AmbiguousWidth::Narrow => s.width(),
+                    AmbiguousWidth::Wide => s.width_cjk(),
+                };
+            }
+            #[cfg(not(feature = "unicode-width"))]
+            return s.chars().count();
+        }
+        graphemes(s)
+            .map(|g| match g.parse::<char>() {
+                // Control characters are counted as a single column
+                Ok(c) if c.is_control() => 1,
+                _ => self.grapheme_width(g),
+            })
+            .sum()
+    }
+
+    /// Returns the width of a grapheme cluster.
+    ///
+    /// Terminals draw a cluster in the cells of its widest character.  Emoji
+    /// presentation (`U+FE0F`) and flags made of two regional indicators
+    /// always take two cells.
+    fn grapheme_width(&self, g: &str) -> usize {
+        let mut chars = g.chars();
+        let Some(first) = chars.next() else {
+            return 0;
+        };
+        let mut width = self.char_width(first);
+        if chars.as_str().is_empty() {
+            return width;
+        }
+        for c in chars {
+            width = width.max(self.char_width(c));
+        }
+        if g.contains('\u{fe0f}') || ('\u{1f1e6}'..='\u{1f1ff}').contains(&first) {
+            width = width.max(2);
+        }
+        width
     }
 }
 
-#[cfg(feature = "ansi-parsing")]
-pub(crate) fn char_width(c: char) -> usize {
-    #[cfg(feature = "unicode-width")]
+fn ambiguous_width_for_locale(locale: &str) -> AmbiguousWidth {
+    match locale.split(['_', '-', '.', '@']).next() {
+        Some("zh" | "ja" | "ko") => AmbiguousWidth::Wide,
+        _ => AmbiguousWidth::Narrow,
+    }
+}
+
+fn width_policy_lock() -> &'static RwLock<WidthPolicy> {
+    static POLICY: OnceLock<RwLock<WidthPolicy>> = OnceLock::new();
+    POLICY.get_or_init(|| RwLock::new(WidthPolicy::from_env()))
+}
+
+/// Returns the width policy of the process.
+pub fn width_policy() -> WidthPolicy {
+    width_policy_lock().read().unwrap().clone()
+}
+
+/// Sets the width policy of the process.
+///
+/// This overrides the default policy which is detected from the
+/// environment with [`WidthPolicy::from_env`].
+pub fn set_width_policy(policy: WidthPolicy) {
+    *width_policy_lock().write().unwrap() = policy;
+}
+
+/// Locks the width policy of the process for reading.
+///
+/// Functions that measure text read the policy once and pass it down
+/// instead of taking the lock for every grapheme.
+pub(crate) fn current_width_policy() -> RwLockReadGuard<'static, WidthPolicy> {
+    width_policy_lock().read().unwrap()
+}
+
+/// Iterates over the grapheme clusters of a string.
+///
+/// Without the `unicode-segmentation` feature every char is treated as a
+/// cluster of its own.
+fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+    #[cfg(feature = "unicode-segmentation")]
     {
-        use unicode_width::UnicodeWidthChar;
-        c.width().unwrap_or(0)
+        unicode_segmentation::UnicodeSegmentation::graphemes(s, true)
     }
-    #[cfg(not(feature = "unicode-width"))]
+    #[cfg(not(feature = "unicode-segmentation"))]
     {
-        let _c = c;
-        1
+        s.char_indices()
+            .map(move |(idx, c)| &s[idx..idx + c.len_utf8()])
     }
 }
 
-#[cfg(not(feature = "ansi-parsing"))]
-pub(crate) fn char_width(_c: char) -> usize {
-    1
-}
-
 /// Truncates a string to a certain number of characters.
 ///
 /// This ensures that escape codes are not screwed up in the process.
@@ -905,7 +1441,11 @@ pub(crate) fn char_width(_c: char) -> usize {
 /// escapes code will still be honored.  If truncation takes place
 /// the tail string will be appended.
 pub fn truncate_str<'a>(s: &'a str, width: usize, tail: &str) -> Cow<'a, str> {
-    if measure_text_width(s) <= width {
+    truncate_end(s, width, tail, &current_width_policy())
+}
+
+fn truncate_end<'a>(s: &'a str, width: usize, tail: &str, policy: &WidthPolicy) -> Cow<'a, str> {
+    if text_width(s, policy) <= width {
         return Cow::Borrowed(s);
     }
 
@@ -920,20 +1460,23 @@ pub fn truncate_str<'a>(s: &'a str, width: usize, tail: &str) -> Cow<'a, str> {
             match item {
                 (s, false) => {
                     if rv.is_none() {
-                        if str_width(s) + length > width.saturating_sub(str_width(tail)) {
+                        if policy.str_width(s) + length
+                            > width.saturating_sub(policy.str_width(tail))
+                        {
                             let ts = iter.current_slice();
 
                             let mut s_byte = 0;
                             let mut s_width = 0;
-                            let rest_width =
-                                width.saturating_sub(str_width(tail)).saturating_sub(length);
-                            for c in s.chars() {
-                                s_byte += c.len_utf8();
-                                s_width += char_width(c);
+                            let rest_width = width
+                                .saturating_sub(policy.str_width(tail))
+                                .saturating_sub(length);
+                            for g in graphemes(s) {
+                                s_byte += g.len();
+                                s_width += policy.grapheme_width(g);
                                 match s_width.cmp(&rest_width) {
                                     Ordering::Equal => break,
                                     Ordering::Greater => {
-                                        s_byte -= c.len_utf8();
+                                        s_byte -= g.len();
                                         break;
                                     }
                                     Ordering::Less => continue,
@@ -945,7 +1488,7 @@ pub fn truncate_str<'a>(s: &'a str, width: usize, tail: &str) -> Cow<'a, str> {
                             buf.push_str(tail);
                             rv = Some(buf);
                         }
-                        length += str_width(s);
+                        length += policy.str_width(s);
                     }
                 }
                 (s, true) => {
@@ -965,14 +1508,181 @@ pub fn truncate_str<'a>(s: &'a str, width: usize, tail: &str) -> Cow<'a, str> {
 
     #[cfg(not(feature = "ansi-parsing"))]
     {
-        Cow::Owned(format!(
-            "{}{}",
-            &s[..width.saturating_sub(tail.len())],
-            tail
-        ))
+        let rest_width = width.saturating_sub(policy.str_width(tail));
+        let mut s_width = 0;
+        let idx: usize = graphemes(s)
+            .take_while(|g| {
+                s_width += policy.grapheme_width(g);
+                s_width <= rest_width
+            })
+            .map(str::len)
+            .sum();
+        Cow::Owned(format!("{}{}", &s[..idx], tail))
     }
 }
 
+/// Truncates a string to a certain number of characters with a
+/// [`TruncateMode`].
+///
+/// With [`TruncateMode::End`] this behaves like [`truncate_str`].  The other
+/// modes remove the start or the middle of the string instead and put the
+/// tail string in its place.  All escape codes are kept, so styles that are
+/// active where text was removed still apply to the rest of the string.
+///
+/// ```
+/// # use console::{truncate_str_with, TruncateMode};
+/// let path = "src/windows_term/colors.rs";
+/// assert_eq!(truncate_str_with(path, 12, "…", TruncateMode::Start), "…m/colors.rs");
+/// assert_eq!(truncate_str_with(path, 12, "…", TruncateMode::Middle), "src/wi…rs.rs");
+/// ```
+pub fn truncate_str_with<'a>(
+    s: &'a str,
+    width: usize,
+    tail: &str,
+    mode: TruncateMode,
+) -> Cow<'a, str> {
+    truncate_with_policy(s, width, tail, mode, &current_width_policy())
+}
+
+fn truncate_with_policy<'a>(
+    s: &'a str,
+    width: usize,
+    tail: &str,
+    mode: TruncateMode,
+    policy: &WidthPolicy,
+) -> Cow<'a, str> {
+    let total = text_width(s, policy);
+    if total <= width {
+        return Cow::Borrowed(s);
+    }
+
+    let avail = width.saturating_sub(policy.str_width(tail));
+    let (head, end) = match mode {
+        TruncateMode::End => return truncate_end(s, width, tail, policy),
+        TruncateMode::Start => (0, avail),
+        TruncateMode::Middle => (avail - avail / 2, avail / 2),
+    };
+
+    // Text in the columns `head..total - end` is removed and the tail is put
+    // in front of the first character that is kept after that.
+    let end_start = total - end;
+    let mut rv = String::new();
+    let mut col = 0;
+    let mut tail_pending = true;
+    for (item, is_ansi) in ansi_items(s) {
+        if is_ansi {
+            rv.push_str(item);
+            continue;
+        }
+        for g in graphemes(item) {
+            let w = policy.grapheme_width(g);
+            if col + w <= head {
+                rv.push_str(g);
+            } else if col >= end_start {
+                if tail_pending {
+                    rv.push_str(tail);
+                    tail_pending = false;
+                }
+                rv.push_str(g);
+            }
+            col += w;
+        }
+    }
+    if tail_pending {
+        rv.push_str(tail);
+    }
+    Cow::Owned(rv)
+}
+
+/// Slices a string by display columns.
+///
+/// This returns the part of the string that covers the columns in `cols`.
+/// Escape codes are honored: the colors and hyperlink active at the cut
+/// point are restored at the start, and a reset (or a hyperlink close) is
+/// appended if the slice would otherwise leave them active.  Other escape
+/// codes outside of the slice are dropped.  Wide characters that straddle a
+/// boundary are replaced with spaces for the columns that fall into the
+/// slice so the result always lines up.
+///
+/// ```
+/// # #[cfg(all(feature = "ansi-parsing", feature = "unicode-width"))] {
+/// # use console::{slice_str, style};
+/// let s = format!("{} {}", style("foo").red().force_styling(true), "日本語");
+/// assert_eq!(slice_str(&s, 1..5), "\x1b[31moo\x1b[0m  ");
+/// assert_eq!(slice_str(&s, 5..8), " 本");
+/// # }
+/// ```
+pub fn slice_str(s: &str, cols: Range<usize>) -> Cow<'_, str> {
+    let policy = current_width_policy();
+    let mut state = AnsiState::default();
+    // The states at the first and the last column of the slice
+    let mut front = None;
+    let mut back = AnsiState::default();
+    let (mut left_pad, mut right_pad) = (0, 0);
+    let (mut slice_start, mut slice_end) = (0, 0);
+    let mut pos = 0;
+    let mut offset = 0;
+
+    'items: for (item, is_ansi) in ansi_items(s) {
+        if is_ansi {
+            state.apply(item);
+            if front.is_none() {
+                slice_start = offset + item.len();
+                slice_end = slice_start;
+            }
+            offset += item.len();
+            continue;
+        }
+        let mut end = offset;
+        for g in graphemes(item) {
+            let width = policy.grapheme_width(g);
+            end += g.len();
+            if pos < cols.start {
+                pos += width;
+                slice_start = end;
+                slice_end = slice_start;
+                left_pad = pos.saturating_sub(cols.start).min(cols.len());
+                if left_pad > 0 {
+                    front = Some(state.clone());
+                    back = state.clone();
+                    if pos >= cols.end {
+                        break 'items;
+                    }
+                }
+            } else if pos + width > cols.end {
+                right_pad = cols.end.saturating_sub(pos);
+                if right_pad > 0 && front.is_none() {
+                    front = Some(state.clone());
+                    back = state.clone();
+                }
+                break 'items;
+            } else {
+                if front.is_none() {
+                    front = Some(state.clone());
+                }
+                pos += width;
+                slice_end = end;
+                back = state.clone();
+            }
+        }
+        offset += item.len();
+    }
+
+    let front = front.unwrap_or_default();
+    let slice = &s[slice_start..slice_end];
+    if front.is_empty() && back.is_empty() && left_pad == 0 && right_pad == 0 {
+        return Cow::Borrowed(slice);
+    }
+
+    let mut rv = String::new();
+    front.write_prefix(&mut rv);
+    rv.extend(iter::repeat(' ').take(left_pad));
+    rv.push_str(slice);
+    rv.extend(iter::repeat(' ').take(right_pad));
+    back.write_suffix(&mut rv);
+    Cow::Owned(rv)
+}
+
 /// Pads a string to fill a certain number of characters.
 ///
 /// This will honor ansi codes correctly and allows you to align a string
@@ -1000,12 +1710,30 @@ pub fn pad_str_with<'a>(
     truncate: Option<&str>,
     pad: char,
 ) -> Cow<'a, str> {
-    let cols = measure_text_width(s);
+    pad_str_with_mode(s, width, align, truncate, TruncateMode::End, pad)
+}
+
+/// Pads a string with specific padding and truncates it with a
+/// [`TruncateMode`].
+///
+/// This works like [`pad_str_with`], but if truncation is enabled the part
+/// of the string that is removed is picked by `mode` like in
+/// [`truncate_str_with`].
+pub fn pad_str_with_mode<'a>(
+    s: &'a str,
+    width: usize,
+    align: Alignment,
+    truncate: Option<&str>,
+    mode: TruncateMode,
+    pad: char,
+) -> Cow<'a, str> {
+    let policy = current_width_policy();
+    let cols = text_width(s, &policy);
 
     if cols >= width {
         return match truncate {
             None => Cow::Borrowed(s),
-            Some(tail) => truncate_str(s, width, tail),
+            Some(tail) => truncate_with_policy(s, width, tail, mode, &policy),
         };
     }
 
@@ -1028,6 +1756,291 @@ pub fn pad_str_with<'a>(
     Cow::Owned(rv)
 }
 
+/// Options for wrapping text with [`wrap_str_with`] and [`wrap_lines`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrapOptions<'a> {
+    width: usize,
+    initial_indent: &'a str,
+    subsequent_indent: &'a str,
+    tab_width: usize,
+}
+
+impl<'a> WrapOptions<'a> {
+    /// Creates options to wrap text at the given width.
+    pub const fn new(width: usize) -> Self {
+        Self {
+            width,
+            initial_indent: "",
+            subsequent_indent: "",
+            tab_width: 8,
+        }
+    }
+
+    /// Sets the indentation of the first line.
+    pub const fn initial_indent(mut self, indent: &'a str) -> Self {
+        self.initial_indent = indent;
+        self
+    }
+
+    /// Sets the indentation of all lines but the first.
+    ///
+    /// Together with an empty or shorter initial indent this creates a
+    /// hanging indent.
+    pub const fn subsequent_indent(mut self, indent: &'a str) -> Self {
+        self.subsequent_indent = indent;
+        self
+    }
+
+    /// Sets the distance between tab stops.
+    ///
+    /// Tabs are measured like in [`measure_text_width_with`] and default to
+    /// a width of 8.
+    pub const fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+}
+
+/// Wraps a string to a certain number of characters.
+///
+/// This is a shortcut for [`wrap_str_with`] without any indentation.
+pub fn wrap_str(s: &str, width: usize) -> String {
+    wrap_str_with(s, &WrapOptions::new(width))
+}
+
+/// Wraps a string according to the given options.
+///
+/// The lines are joined with `\n`.  See [`wrap_lines`] for details.
+pub fn wrap_str_with(s: &str, options: &WrapOptions<'_>) -> String {
+    wrap_lines(s, options).join("\n")
+}
+
+/// Wraps a string into lines that fit a certain number of characters.
+///
+/// Lines are broken on whitespace and existing newlines are honored.  Words
+/// that are wider than a line are broken between two characters, but never
+/// inside of an escape code.  The width is measured like in
+/// [`measure_text_width`] and includes the indentation, tabs advance to the
+/// next tab stop.  Whitespace at a line break that is inserted by wrapping
+/// is dropped, whitespace at the start or the end of an input line only if
+/// it does not fit.
+///
+/// Styles and hyperlinks that are active at a line break are closed at the
+/// end of the line and opened again after the indentation of the next line,
+/// so every line can be printed on its own.
+///
+/// ```
+/// # use console::{wrap_lines, WrapOptions};
+/// let options = WrapOptions::new(12).subsequent_indent("  ");
+/// assert_eq!(
+///     wrap_lines("a quick brown fox jumps", &options),
+///     ["a quick", "  brown fox", "  jumps"]
+/// );
+/// ```
+pub fn wrap_lines(s: &str, options: &WrapOptions<'_>) -> Vec<String> {
+    let policy = current_width_policy();
+    let mut wrapper = Wrapper::new(options, &policy);
+    let mut whitespace = String::new();
+    let mut word = String::new();
+
+    for (item, is_ansi) in ansi_items(s) {
+        if is_ansi {
+            word.push_str(item);
+            continue;
+        }
+        for c in item.chars() {
+            if c == '\n' {
+                wrapper.push_word(&whitespace, &word);
+                whitespace.clear();
+                word.clear();
+                wrapper.break_line(false);
+            } else if c.is_whitespace() {
+                if text_width(&word, &policy) > 0 {
+                    wrapper.push_word(&whitespace, &word);
+                    whitespace.clear();
+                    word.clear();
+                }
+                whitespace.push(c);
+            } else {
+                word.push(c);
+            }
+        }
+    }
+    wrapper.push_word(&whitespace, &word);
+    wrapper.finish()
+}
+
+struct Wrapper<'a> {
+    options: &'a WrapOptions<'a>,
+    policy: &'a WidthPolicy,
+    lines: Vec<String>,
+    line: String,
+    line_width: usize,
+    has_content: bool,
+    /// Whether the current line was started by a break inserted by wrapping.
+    wrapped: bool,
+    state: AnsiState,
+}
+
+impl<'a> Wrapper<'a> {
+    fn new(options: &'a WrapOptions<'a>, policy: &'a WidthPolicy) -> Self {
+        Self {
+            options,
+            policy,
+            lines: Vec::new(),
+            line: options.initial_indent.to_string(),
+            line_width: text_width(options.initial_indent, policy),
+            has_content: false,
+            wrapped: false,
+            state: AnsiState::default(),
+        }
+    }
+
+    fn push_word(&mut self, whitespace: &str, word: &str) {
+        let word_width = text_width(word, self.policy);
+        let fits = |slf: &Self, extra| slf.line_width + extra <= slf.options.width;
+
+        if word_width > 0 {
+            let col = self.advance(whitespace);
+            let word_fits = col + word_width <= self.options.width;
+            if self.has_content && !word_fits {
+                self.break_line(true);
+            } else if self.has_content || (!self.wrapped && word_fits) {
+                // Leading whitespace is dropped at wrapped breaks or if the word doesn't fit
+                self.line.push_str(whitespace);
+                self.line_width = col;
+            }
+        } else if self.has_content || !self.wrapped {
+            // Whitespace at the start or the end of a line is only kept as far as it fits
+            for g in graphemes(whitespace) {
+                let col = self.advance(g);
+                if col > self.options.width {
+                    break;
+                }
+                self.line.push_str(g);
+                self.line_width = col;
+            }
+        }
+
+        for (item, is_ansi) in ansi_items(word) {
+            if is_ansi {
+                self.push_escape(item);
+                continue;
+            }
+            if fits(self, self.policy.str_width(item)) {
+                self.push_text(item);
+                continue;
+            }
+            // The word is too long for a line of its own, break it up
+            for g in graphemes(item) {
+                if self.has_content && !fits(self, self.policy.grapheme_width(g)) {
+                    self.break_line(true);
+                }
+                self.push_text(g);
+            }
+        }
+    }
+
+    /// Returns the column after printing whitespace at the end of the line.
+    fn advance(&self, whitespace: &str) -> usize {
+        graphemes(whitespace).fold(self.line_width, |col, g| {
+            advance_column(col, g, self.options.tab_width, self.policy)
+        })
+    }
+
+    fn push_text(&mut self, s: &str) {
+        self.line.push_str(s);
+        self.line_width += self.policy.str_width(s);
+        self.has_content = true;
+    }
+
+    fn push_escape(&mut self, escape: &str) {
+        self.line.push_str(escape);
+        self.state.apply(escape);
+    }
+
+    fn break_line(&mut self, wrapped: bool) {
+        self.state.write_suffix(&mut self.line);
+        let mut next = self.options.subsequent_indent.to_string();
+        self.state.write_prefix(&mut next);
+        self.lines.push(mem::replace(&mut self.line, next));
+        self.line_width = text_width(self.options.subsequent_indent, self.policy);
+        self.has_content = false;
+        self.wrapped = wrapped;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.state.write_suffix(&mut self.line);
+        self.lines.push(self.line);
+        self.lines
+    }
+}
+
+/// The colors and the hyperlink that are active at some point of a string.
+#[derive(Debug, Clone, Default)]
+struct AnsiState {
+    style: Style,
+    /// The OSC 8 escape code that opened the active hyperlink.
+    link: Option<String>,
+}
+
+impl AnsiState {
+    /// Updates the state with an escape code from the string.
+    fn apply(&mut self, escape: &str) {
+        #[cfg(feature = "ansi-parsing")]
+        match AnsiToken::from_escape(escape) {
+            AnsiToken::Osc(osc) if osc.command == "8" => {
+                let uri = osc.payload.split_once(';').map_or("", |(_, uri)| uri);
+                self.link = (!uri.is_empty()).then(|| escape.to_string());
+            }
+            _ => self.style = mem::take(&mut self.style).apply_escape(escape),
+        }
+        #[cfg(not(feature = "ansi-parsing"))]
+        let _ = escape;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.style == Style::new() && self.link.is_none()
+    }
+
+    /// Writes the escape codes that restore this state.
+    fn write_prefix(&self, rv: &mut String) {
+        let _ = self.style.write_prefix(rv);
+        if let Some(ref link) = self.link {
+            rv.push_str(link);
+        }
+    }
+
+    /// Writes the escape codes that end this state.
+    fn write_suffix(&self, rv: &mut String) {
+        if self.style != Style::new() {
+            rv.push_str("\x1b[0m");
+        }
+        if let Some(ref link) = self.link {
+            // Close the link with the same terminator it was opened with
+            rv.push_str(if link.ends_with('\x07') {
+                "\x1b]8;;\x07"
+            } else {
+                "\x1b]8;;\x1b\\"
+            });
+        }
+    }
+}
+
+/// Iterates over the text and ansi codes of a string like [`AnsiCodeIterator`].
+///
+/// Without ansi parsing the whole string is treated as text.
+fn ansi_items(s: &str) -> impl Iterator<Item = (&str, bool)> {
+    #[cfg(feature = "ansi-parsing")]
+    {
+        AnsiCodeIterator::new(s)
+    }
+    #[cfg(not(feature = "ansi-parsing"))]
+    {
+        iter::once((s, false))
+    }
+}
+
 #[test]
 fn test_text_width() {
     let s = style("foo")
@@ -1062,6 +2075,57 @@ fn test_text_width() {
     );
 }
 
+#[test]
+fn test_text_width_of_control_runs() {
+    // control characters are measured together with the text around them
+    let expected = current_width_policy().str_width("a\r\nb");
+    assert_eq!(measure_text_width("a\r\nb"), expected);
+    if cfg!(all(
+        feature = "unicode-width",
+        not(feature = "unicode-segmentation")
+    )) {
+        assert_eq!(expected, 3);
+    }
+    if cfg!(feature = "ansi-parsing") {
+        assert_eq!(measure_text_width("\x1b[1ma\r\nb\x1b[0m"), expected);
+    }
+}
+
+#[test]
+fn test_text_width_with_controls() {
+    assert_eq!(measure_text_width_with("", 8), 0);
+    assert_eq!(measure_text_width_with("\t", 8), 8);
+    assert_eq!(measure_text_width_with("abcdefgh\t", 8), 16);
+    assert_eq!(measure_text_width_with("a\tb\tc", 3), 7);
+    assert_eq!(measure_text_width_with("a\tb", 0), 2);
+    assert_eq!(measure_text_width_with("abc\rx", 8), 3);
+    assert_eq!(measure_text_width_with("ab\nabcd\r\nx", 8), 4);
+    assert_eq!(measure_text_width_with("abc\x08\x08x", 8), 3);
+    assert_eq!(measure_text_width_with("\x08\x08ab\x07", 8), 2);
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_text_width_with_ansi() {
+    let s = format!("{}\t|", style("ab").red().force_styling(true));
+    assert_eq!(measure_text_width_with(&s, 4), 5);
+    assert_eq!(expand_tabs(&s, 4), "\x1b[31mab\x1b[0m  |");
+    let s = "\x1b]8;;http://x\x1b\\a\tb";
+    assert_eq!(expand_tabs(s, 2), "\x1b]8;;http://x\x1b\\a b");
+}
+
+#[test]
+fn test_expand_tabs() {
+    assert!(matches!(
+        expand_tabs("foo bar", 8),
+        Cow::Borrowed("foo bar")
+    ));
+    assert_eq!(expand_tabs("\tfoo\tbar", 8), "        foo     bar");
+    assert_eq!(expand_tabs("ab\rc\td", 4), "ab\rc   d");
+    assert_eq!(expand_tabs("a\nb\tc", 4), "a\nb   c");
+    assert_eq!(expand_tabs("a\tb", 0), "ab");
+}
+
 #[test]
 #[cfg(all(feature = "unicode-width", feature = "ansi-parsing"))]
 fn test_truncate_str() {
@@ -1097,6 +2161,53 @@ fn test_truncate_str() {
     );
 }
 
+#[test]
+fn test_truncate_str_with_no_ansi() {
+    let s = "foo bar baz";
+    assert_eq!(&truncate_str_with(s, 11, "~", TruncateMode::Start), s);
+    assert_eq!(&truncate_str_with(s, 6, "~", TruncateMode::End), "foo b~");
+    assert_eq!(&truncate_str_with(s, 6, "~", TruncateMode::Start), "~r baz");
+    assert_eq!(
+        &truncate_str_with(s, 6, "~", TruncateMode::Middle),
+        "foo~az"
+    );
+    assert_eq!(
+        &truncate_str_with(s, 7, "~", TruncateMode::Middle),
+        "foo~baz"
+    );
+    assert_eq!(&truncate_str_with(s, 2, "...", TruncateMode::Start), "...");
+    assert_eq!(&truncate_str_with(s, 0, "", TruncateMode::Middle), "");
+}
+
+#[test]
+#[cfg(all(feature = "unicode-width", feature = "ansi-parsing"))]
+fn test_truncate_str_with() {
+    let s = format!("{} baz", style("foo bar").red().force_styling(true));
+    assert_eq!(
+        &truncate_str_with(&s, 6, "~", TruncateMode::Start),
+        "\x1b[31m~r\x1b[0m baz"
+    );
+    assert_eq!(
+        &truncate_str_with(&s, 6, "~", TruncateMode::Middle),
+        "\x1b[31mfoo\x1b[0m~az"
+    );
+    let s = "バーバー";
+    assert_eq!(&truncate_str_with(s, 5, "", TruncateMode::Start), "バー");
+    assert_eq!(&truncate_str_with(s, 5, "", TruncateMode::Middle), "バー");
+    assert_eq!(&truncate_str_with(s, 6, "!", TruncateMode::Middle), "バ!ー");
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_osc_width_and_truncate() {
+    let s = "\x1b]0;title\x07\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ text";
+    assert_eq!(measure_text_width(s), 9);
+    assert_eq!(
+        &truncate_str(s, 6, "!"),
+        "\x1b]0;title\x07\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ !"
+    );
+}
+
 #[test]
 fn test_truncate_str_no_ansi() {
     assert_eq!(&truncate_str("foo bar", 7, "!"), "foo bar");
@@ -1109,6 +2220,110 @@ fn test_truncate_str_no_ansi() {
     assert_eq!(&truncate_str("ab", 2, "!!!"), "ab");
 }
 
+#[test]
+#[cfg(feature = "unicode-segmentation")]
+fn test_grapheme_clusters() {
+    let s = "👩\u{200d}💻 🇩🇪 👍🏽 e\u{301} ❤\u{fe0f}";
+    assert_eq!(measure_text_width(s), 13);
+    assert_eq!(
+        truncate_str("👩\u{200d}💻👩\u{200d}💻", 3, ""),
+        "👩\u{200d}💻"
+    );
+    assert_eq!(truncate_str("🇩🇪🇫🇷", 3, "!"), "🇩🇪!");
+    assert_eq!(truncate_str("ae\u{301}o", 2, ""), "ae\u{301}");
+    assert_eq!(
+        truncate_str_with("🇩🇪🇫🇷🇮🇹", 4, "", TruncateMode::Start),
+        "🇫🇷🇮🇹"
+    );
+    assert_eq!(pad_str("👍🏽", 4, Alignment::Center, None), " 👍🏽 ");
+    assert_eq!(slice_str("a🇩🇪b", 1..3), "🇩🇪");
+}
+
+#[test]
+fn test_width_policy() {
+    let policy = WidthPolicy::new().override_width('a'..='c', 0);
+    assert_eq!(policy.char_width('a'), 0);
+    assert_eq!(policy.char_width('d'), 1);
+    assert_eq!(policy.str_width("abcdef"), 3);
+    assert_eq!(policy.str_width("a\nb"), 1);
+    let policy = policy.override_width('b'..='b', 3);
+    assert_eq!(policy.str_width("abcdef"), 6);
+    assert_eq!(WidthPolicy::default(), WidthPolicy::new());
+}
+
+#[test]
+#[cfg(feature = "unicode-width")]
+fn test_width_policy_ambiguous() {
+    let narrow = WidthPolicy::new();
+    let wide = WidthPolicy::new().ambiguous(AmbiguousWidth::Wide);
+    assert_eq!(narrow.str_width("±○─"), 3);
+    assert_eq!(wide.str_width("±○─"), 6);
+    assert_eq!(wide.str_width("abc"), 3);
+    let wide = wide.override_width('\u{2500}'..='\u{257f}', 1);
+    assert_eq!(wide.str_width("±○─"), 5);
+    assert_eq!(wide.char_width('日'), 2);
+}
+
+#[test]
+fn test_ambiguous_width_for_locale() {
+    for locale in ["ja_JP.UTF-8", "zh_TW", "ko", "zh-Hans"] {
+        assert_eq!(ambiguous_width_for_locale(locale), AmbiguousWidth::Wide);
+    }
+    for locale in ["", "C", "POSIX", "en_US.UTF-8", "de_DE@euro", "jam"] {
+        assert_eq!(ambiguous_width_for_locale(locale), AmbiguousWidth::Narrow);
+    }
+}
+
+#[test]
+fn test_slice_str_no_ansi() {
+    assert_eq!(slice_str("foo bar", 0..7), "foo bar");
+    assert!(matches!(slice_str("foo bar", 2..5), Cow::Borrowed("o b")));
+    assert_eq!(slice_str("foo bar", 4..100), "bar");
+    assert_eq!(slice_str("foo bar", 10..12), "");
+    assert_eq!(slice_str("foo bar", 3..3), "");
+}
+
+#[test]
+#[cfg(all(feature = "unicode-width", feature = "ansi-parsing"))]
+fn test_slice_str_wide_chars() {
+    assert_eq!(slice_str("日本語", 0..6), "日本語");
+    assert_eq!(slice_str("日本語", 1..5), " 本 ");
+    assert_eq!(slice_str("日本語", 1..2), " ");
+    assert_eq!(slice_str("日本語", 2..3), " ");
+    assert_eq!(slice_str("a日b", 2..4), " b");
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_slice_str() {
+    let s = format!(
+        "{}{}",
+        style("foo").red().force_styling(true),
+        style("bar").bold().force_styling(true)
+    );
+    assert_eq!(slice_str(&s, 0..3), "\x1b[31mfoo\x1b[0m");
+    assert_eq!(slice_str(&s, 2..4), "\x1b[31mo\x1b[0m\x1b[1mb\x1b[0m");
+    assert_eq!(slice_str(&s, 4..6), "\x1b[1mar\x1b[0m");
+
+    // An unterminated style gets a reset
+    assert_eq!(slice_str("\x1b[32mfoo bar", 4..6), "\x1b[32mba\x1b[0m");
+
+    // Hyperlinks stay open across the cut
+    let s = "\x1b]8;;https://example.com\x07link\x1b]8;;\x07 text";
+    assert_eq!(
+        slice_str(s, 2..7),
+        "\x1b]8;;https://example.com\x07nk\x1b]8;;\x07 te"
+    );
+    assert_eq!(
+        slice_str(s, 1..3),
+        "\x1b]8;;https://example.com\x07in\x1b]8;;\x07"
+    );
+
+    // Only the state at the cut points is carried over, other codes are dropped
+    let s = "\x1b[2J\x1b[Htitle\x1b]0;t\x07\x1b[31mabc\x1b[1K";
+    assert_eq!(slice_str(s, 5..7), "\x1b[31mab\x1b[0m");
+}
+
 #[test]
 fn test_pad_str() {
     assert_eq!(pad_str("foo", 7, Alignment::Center, None), "  foo  ");
@@ -1152,6 +2367,150 @@ fn test_pad_str_with() {
     );
 }
 
+#[test]
+fn test_wrap_str() {
+    assert_eq!(wrap_str("foo bar baz", 7), "foo bar\nbaz");
+    assert_eq!(wrap_str("foo   bar", 20), "foo   bar");
+    assert_eq!(wrap_str("foo bar", 3), "foo\nbar");
+    assert_eq!(wrap_str("foo\n\nbar baz", 5), "foo\n\nbar\nbaz");
+    assert_eq!(wrap_str("foobarbaz", 4), "foob\narba\nz");
+    assert_eq!(wrap_str("", 4), "");
+    assert_eq!(wrap_lines("foo bar", &WrapOptions::new(6)), ["foo", "bar"]);
+    assert_eq!(wrap_str("foo bar ", 3), "foo\nbar");
+    assert_eq!(wrap_str("foo   \nbar", 4), "foo \nbar");
+}
+
+#[test]
+fn test_wrap_str_leading_whitespace() {
+    assert_eq!(wrap_str("  indented text", 20), "  indented text");
+    assert_eq!(
+        wrap_str("Usage:\n    foo --bar", 40),
+        "Usage:\n    foo --bar"
+    );
+    assert_eq!(wrap_str("  indented text", 10), "  indented\ntext");
+    assert_eq!(wrap_str("a\n      b", 4), "a\nb");
+    assert_eq!(wrap_str("a\n      \nb", 4), "a\n    \nb");
+}
+
+#[test]
+fn test_wrap_str_tabs() {
+    assert_eq!(wrap_str("a\tb", 8), "a\nb");
+    assert_eq!(wrap_str("a\tb", 9), "a\tb");
+    let options = WrapOptions::new(5).tab_width(4);
+    assert_eq!(wrap_str_with("a\tb c", &options), "a\tb\nc");
+    assert_eq!(wrap_str_with("abc\t\nd", &options), "abc\t\nd");
+}
+
+#[test]
+fn test_wrap_str_with_indent() {
+    let options = WrapOptions::new(8)
+        .initial_indent("* ")
+        .subsequent_indent("  ");
+    assert_eq!(
+        wrap_str_with("one two three four", &options),
+        "* one\n  two\n  three\n  four"
+    );
+    let options = WrapOptions::new(6).subsequent_indent("  ");
+    assert_eq!(wrap_str_with("abcdefghij", &options), "abcdef\n  ghij");
+}
+
+#[test]
+#[cfg(all(feature = "ansi-parsing", feature = "unicode-width"))]
+fn test_wrap_str_wide_chars() {
+    assert_eq!(wrap_str("💩💩💩", 5), "💩💩\n💩");
+    assert_eq!(wrap_str("ab 💩💩", 4), "ab\n💩💩");
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_wrap_str_ansi() {
+    assert_eq!(
+        wrap_str("\x1b[31mfoo bar\x1b[0m baz", 4),
+        "\x1b[31mfoo\x1b[0m\n\x1b[31mbar\x1b[0m\nbaz"
+    );
+    assert_eq!(
+        wrap_str("\x1b[1mfoo\x1b[31mbar\x1b[22m", 3),
+        "\x1b[1mfoo\x1b[31m\x1b[0m\n\x1b[31m\x1b[1mbar\x1b[22m\x1b[0m"
+    );
+    assert_eq!(
+        wrap_str("\x1b[31mfoo \x1b[32mbar \x1b[33mbaz", 3),
+        "\x1b[31mfoo\x1b[0m\n\x1b[31m\x1b[32mbar\x1b[0m\n\x1b[32m\x1b[33mbaz\x1b[0m"
+    );
+    let options = WrapOptions::new(5).subsequent_indent("> ");
+    assert_eq!(
+        wrap_str_with("\x1b]8;;http://x\x1b\\abcdef\x1b]8;;\x1b\\", &options),
+        "\x1b]8;;http://x\x1b\\abcde\x1b]8;;\x1b\\\n> \x1b]8;;http://x\x1b\\f\x1b]8;;\x1b\\"
+    );
+    assert_eq!(
+        wrap_str("\x1b]8;;http://x\x07\x1b[1mab cd", 3),
+        "\x1b]8;;http://x\x07\x1b[1mab\x1b[0m\x1b]8;;\x07\n\x1b[1m\x1b]8;;http://x\x07cd\x1b[0m\x1b]8;;\x07"
+    );
+    for line in wrap_lines("\x1b[4ma b c d e f\x1b[0m", &WrapOptions::new(3)) {
+        assert!(measure_text_width(&line) <= 3);
+        assert!(line.starts_with("\x1b[4m"));
+        assert!(line.ends_with("\x1b[0m"));
+    }
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_styled_spans() {
+    let s = "a\x1b[1;3;38;5;208mb\x1b[22;48;2;1;2;3mc\x1b[2Kd\x1b[92;49;23me\x1b[mf";
+    let spans: Vec<_> = StyledSpans::new(s).collect();
+    assert_eq!(
+        spans,
+        [
+            (Style::new(), "a"),
+            (Style::new().bold().italic().color256(208), "b"),
+            (
+                Style::new().italic().color256(208).on_true_color(1, 2, 3),
+                "c"
+            ),
+            (
+                Style::new().italic().color256(208).on_true_color(1, 2, 3),
+                "d"
+            ),
+            (Style::new().green().bright(), "e"),
+            (Style::new(), "f"),
+        ]
+    );
+
+    // Malformed extended colors stop processing the sequence
+    let spans: Vec<_> = StyledSpans::new("\x1b[1;38;7;31mx").collect();
+    assert_eq!(spans, [(Style::new().bold(), "x")]);
+}
+
+#[test]
+#[cfg(feature = "ansi-parsing")]
+fn test_styled_spans_roundtrip() {
+    let styles = [
+        Style::new().red().on_blue().underlined(),
+        Style::new().cyan().bright().on_white().on_bright(),
+        Style::new()
+            .color256(33)
+            .on_color256(3)
+            .blink()
+            .strikethrough(),
+        Style::new().true_color(1, 2, 3).reverse().hidden().dim(),
+    ];
+    for style in styles {
+        let style = style.force_styling(true);
+        let s = style.apply_to("text").to_string();
+        let spans: Vec<_> = StyledSpans::new(&s).collect();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].1, "text");
+        assert_eq!(
+            spans[0]
+                .0
+                .clone()
+                .force_styling(true)
+                .apply_to("text")
+                .to_string(),
+            s
+        );
+    }
+}
+
 #[test]
 fn test_attributes_single() {
     for attr in Attribute::MAP {
@@ -0,0 +1,545 @@
+use alloc::{string::String, vec::Vec};
+use core::mem;
+use std::io;
+
+use crate::ansi::{AnsiEvent, AnsiStreamParser, AnsiToken, CsiSequence, EscSequence};
+use crate::utils::{current_width_policy, Style, WidthPolicy};
+
+/// A single cell of a [`VirtualTerminal`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    fn blank() -> Cell {
+        Cell {
+            text: " ".into(),
+            style: Style::new(),
+        }
+    }
+
+    /// Returns the text in the cell.
+    ///
+    /// This is usually a single character followed by any combining
+    /// characters.  The cell to the right of a wide character is empty.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the style of the cell.
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+}
+
+/// An in-memory terminal that interprets the output of a program.
+///
+/// The terminal keeps a grid of styled cells, the cursor position and the
+/// lines that were scrolled off the top of the screen.  It understands
+/// text (including wide characters and line wrapping), the control
+/// characters `\r`, `\n`, `\t` and backspace, SGR sequences, the CSI
+/// sequences for moving the cursor and erasing, and the escapes for saving
+/// the cursor and scrolling.  Newlines also return to the first column like
+/// a terminal with the default output processing would.  Everything else
+/// is ignored.
+///
+/// This makes it possible to test redraw logic without a real terminal:
+///
+/// ```
+/// # use console::VirtualTerminal;
+/// let mut vt = VirtualTerminal::new(20, 4);
+/// vt.feed(b"one\ntwo\nthree\n\x1b[2A\r\x1b[2Kfour");
+/// assert_eq!(vt.contents(), "one\nfour\nthree");
+/// assert_eq!(vt.cursor_position(), (4, 1));
+/// ```
+#[derive(Debug)]
+pub struct VirtualTerminal {
+    parser: AnsiStreamParser,
+    screen: Screen,
+}
+
+impl VirtualTerminal {
+    /// Creates a new terminal with `width` columns and `height` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is zero.
+    pub fn new(width: usize, height: usize) -> VirtualTerminal {
+        assert!(width > 0 && height > 0, "terminal size must not be zero");
+        VirtualTerminal {
+            parser: AnsiStreamParser::new(),
+            screen: Screen::new(width, height),
+        }
+    }
+
+    /// Feeds output to the terminal.
+    ///
+    /// Escape codes and characters may be split across calls.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let screen = &mut self.screen;
+        self.parser.feed(bytes, |event| match event {
+            AnsiEvent::Text(text) => screen.print(text),
+            AnsiEvent::Escape(escape) => screen.escape(escape),
+        });
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.screen.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.screen.height
+    }
+
+    /// Returns the cursor position as `(column, row)`, both 0-based.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let screen = &self.screen;
+        (screen.col.min(screen.width - 1), screen.row)
+    }
+
+    /// Returns `true` unless the cursor was hidden.
+    pub fn is_cursor_visible(&self) -> bool {
+        self.screen.cursor_visible
+    }
+
+    /// Returns the cell at the given column and row.
+    pub fn cell(&self, x: usize, y: usize) -> Option<&Cell> {
+        self.screen.lines.get(y)?.get(x)
+    }
+
+    /// Returns the text of a row without trailing whitespace.
+    pub fn line(&self, y: usize) -> Option<String> {
+        self.screen.lines.get(y).map(|line| line_text(line))
+    }
+
+    /// Returns the text of the screen.
+    ///
+    /// Rows are separated by newlines, trailing whitespace and empty rows
+    /// at the bottom are left out.
+    pub fn contents(&self) -> String {
+        let mut lines: Vec<_> = self.screen.lines.iter().map(|l| line_text(l)).collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+
+    /// Returns the text of the rows that were scrolled off the screen.
+    pub fn scrollback(&self) -> Vec<String> {
+        self.screen
+            .scrollback
+            .iter()
+            .map(|l| line_text(l))
+            .collect()
+    }
+}
+
+impl io::Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn line_text(line: &[Cell]) -> String {
+    let text: String = line.iter().map(|cell| cell.text.as_str()).collect();
+    text.trim_end().into()
+}
+
+#[derive(Debug)]
+struct Screen {
+    width: usize,
+    height: usize,
+    lines: Vec<Vec<Cell>>,
+    scrollback: Vec<Vec<Cell>>,
+    row: usize,
+    /// The cursor column, equal to `width` after the last column was written.
+    col: usize,
+    saved: (usize, usize),
+    style: Style,
+    cursor_visible: bool,
+}
+
+impl Screen {
+    fn new(width: usize, height: usize) -> Screen {
+        Screen {
+            width,
+            height,
+            lines: vec![vec![Cell::blank(); width]; height],
+            scrollback: Vec::new(),
+            row: 0,
+            col: 0,
+            saved: (0, 0),
+            style: Style::new(),
+            cursor_visible: true,
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        let policy = current_width_policy();
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.col = 0;
+                    self.line_feed();
+                }
+                '\r' => self.col = 0,
+                '\x08' => self.col = self.col.min(self.width - 1).saturating_sub(1),
+                '\t' => self.col = ((self.col / 8 + 1) * 8).min(self.width - 1),
+                c if c.is_control() => {}
+                c => self.put_char(c, &policy),
+            }
+        }
+    }
+
+    fn put_char(&mut self, c: char, policy: &WidthPolicy) {
+        let width = policy.char_width(c);
+        if width == 0 {
+            // Combining characters go to the previously written cell
+            let col = self.col.saturating_sub(1).min(self.width - 1);
+            let line = &mut self.lines[self.row];
+            let col = if line[col].text.is_empty() && col > 0 {
+                col - 1
+            } else {
+                col
+            };
+            line[col].text.push(c);
+            return;
+        }
+        if self.col + width > self.width {
+            self.col = 0;
+            self.line_feed();
+        }
+        let width = width.min(self.width);
+        self.clear_wide(self.col);
+        self.clear_wide(self.col + width - 1);
+        let line = &mut self.lines[self.row];
+        line[self.col] = Cell {
+            text: c.into(),
+            style: self.style.clone(),
+        };
+        for cell in &mut line[self.col + 1..self.col + width] {
+            *cell = Cell {
+                text: String::new(),
+                style: self.style.clone(),
+            };
+        }
+        self.col += width;
+    }
+
+    /// Blanks both halves of a wide character that covers the column.
+    fn clear_wide(&mut self, col: usize) {
+        let line = &mut self.lines[self.row];
+        let Some(cell) = line.get(col) else {
+            return;
+        };
+        let start = if cell.text.is_empty() {
+            col.saturating_sub(1)
+        } else {
+            col
+        };
+        if line.get(start + 1).is_some_and(|cell| cell.text.is_empty()) {
+            line[start] = Cell::blank();
+            line[start + 1] = Cell::blank();
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.row + 1 == self.height {
+            self.scroll_up();
+        } else {
+            self.row += 1;
+        }
+    }
+
+    fn scroll_up(&mut self) {
+        let line = self.lines.remove(0);
+        self.scrollback.push(line);
+        self.lines.push(vec![Cell::blank(); self.width]);
+    }
+
+    fn scroll_down(&mut self) {
+        self.lines.pop();
+        self.lines.insert(0, vec![Cell::blank(); self.width]);
+    }
+
+    fn escape(&mut self, escape: &str) {
+        match AnsiToken::from_escape(escape) {
+            AnsiToken::Csi(csi) => self.csi(escape, &csi),
+            AnsiToken::Esc(esc) => self.esc(&esc),
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, esc: &EscSequence<'_>) {
+        if !esc.intermediates.is_empty() {
+            return;
+        }
+        match esc.final_char {
+            '7' => self.saved = (self.col, self.row),
+            '8' => (self.col, self.row) = self.saved,
+            'D' => self.line_feed(),
+            'E' => {
+                self.col = 0;
+                self.line_feed();
+            }
+            'M' if self.row == 0 => self.scroll_down(),
+            'M' => self.row -= 1,
+            'c' => *self = Screen::new(self.width, self.height),
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, raw: &str, csi: &CsiSequence<'_>) {
+        if !csi.intermediates.is_empty() {
+            return;
+        }
+        if csi.params.private_marker() == Some('?') {
+            if csi.params.iter().any(|param| param == Some(25)) {
+                match csi.final_char {
+                    'h' => self.cursor_visible = true,
+                    'l' => self.cursor_visible = false,
+                    _ => {}
+                }
+            }
+            return;
+        } else if csi.params.private_marker().is_some() {
+            return;
+        }
+
+        let mut params = csi.params.iter();
+        let mut next = |default: usize| match params.next().flatten() {
+            Some(0) | None => default,
+            Some(n) => n as usize,
+        };
+        // Any sequence but SGR cancels a pending line wrap
+        let col = self.col.min(self.width - 1);
+        if csi.final_char != 'm' {
+            self.col = col;
+        }
+        let (max_col, max_row) = (self.width - 1, self.height - 1);
+        match csi.final_char {
+            'A' => self.row = self.row.saturating_sub(next(1)),
+            'B' => self.row = (self.row + next(1)).min(max_row),
+            'C' => self.col = (col + next(1)).min(max_col),
+            'D' => self.col = col.saturating_sub(next(1)),
+            'E' => (self.col, self.row) = (0, (self.row + next(1)).min(max_row)),
+            'F' => (self.col, self.row) = (0, self.row.saturating_sub(next(1))),
+            'G' => self.col = (next(1) - 1).min(max_col),
+            'H' | 'f' => {
+                self.row = (next(1) - 1).min(max_row);
+                self.col = (next(1) - 1).min(max_col);
+            }
+            'J' => match params.next().flatten().unwrap_or(0) {
+                0 => {
+                    self.erase_line(col, self.width);
+                    self.erase_lines(self.row + 1, self.height);
+                }
+                1 => {
+                    self.erase_lines(0, self.row);
+                    self.erase_line(0, col + 1);
+                }
+                2 => self.erase_lines(0, self.height),
+                3 => self.scrollback.clear(),
+                _ => {}
+            },
+            'K' => match params.next().flatten().unwrap_or(0) {
+                0 => self.erase_line(col, self.width),
+                1 => self.erase_line(0, col + 1),
+                2 => self.erase_line(0, self.width),
+                _ => {}
+            },
+            'P' => {
+                let n = next(1).min(self.width - col);
+                let line = &mut self.lines[self.row];
+                line.drain(col..col + n);
+                line.extend(core::iter::repeat(Cell::blank()).take(n));
+            }
+            'm' => self.style = mem::take(&mut self.style).apply_escape(raw),
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, start: usize, end: usize) {
+        for col in start..end {
+            self.clear_wide(col);
+            self.lines[self.row][col] = Cell::blank();
+        }
+    }
+
+    fn erase_lines(&mut self, start: usize, end: usize) {
+        for line in &mut self.lines[start..end] {
+            line.fill(Cell::blank());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{style, StreamFeatures, Term};
+    use alloc::sync::Arc;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone)]
+    struct SharedTerminal(Arc<Mutex<VirtualTerminal>>);
+
+    impl io::Write for SharedTerminal {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn render(width: usize, height: usize, input: &str) -> VirtualTerminal {
+        let mut vt = VirtualTerminal::new(width, height);
+        vt.feed(input.as_bytes());
+        vt
+    }
+
+    #[test]
+    fn test_text_and_wrapping() {
+        let vt = render(4, 3, "abcdefgh\r\nxy\rz\tq");
+        assert_eq!(vt.contents(), "abcd\nefgh\nzy q");
+        assert_eq!(vt.cursor_position(), (3, 2));
+
+        let vt = render(4, 2, "abcd");
+        assert_eq!(vt.cursor_position(), (3, 0));
+        assert_eq!(vt.contents(), "abcd");
+    }
+
+    #[test]
+    fn test_scrollback() {
+        let vt = render(10, 2, "one\ntwo\nthree\nfour");
+        assert_eq!(vt.scrollback(), ["one", "two"]);
+        assert_eq!(vt.contents(), "three\nfour");
+
+        let vt = render(10, 2, "one\ntwo\x1bD");
+        assert_eq!(vt.scrollback(), ["one"]);
+        assert_eq!(vt.contents(), "two");
+        let vt = render(10, 2, "one\ntwo\x1bD\x1b[3J\x1b[H\x1bM");
+        assert!(vt.scrollback().is_empty());
+        assert_eq!(vt.contents(), "\ntwo");
+    }
+
+    #[test]
+    fn test_cursor_movement() {
+        let vt = render(10, 5, "\x1b[3;4Hx\x1b[2Ay\x1b[10Cz\x1b[Bw\x1b[3Dv\x1b[5Gu");
+        assert_eq!(vt.contents(), "    y    z\n    u v  w\n   x");
+        assert_eq!(vt.line(1).unwrap(), "    u v  w");
+        assert_eq!(vt.line(5), None);
+        assert_eq!(vt.cursor_position(), (5, 1));
+
+        let vt = render(
+            10,
+            5,
+            "ab\x1b7\n\ncd\x1b8e\x1b[2Ef\x1b[Fg\x1b[99;99Hh\x1b[Hi",
+        );
+        assert_eq!(vt.contents(), "ibe\ng\nfd\n\n         h");
+        let vt = render(10, 3, "a\x1bMb\x1b[10Bc\x1bDd\x1bEe");
+        assert_eq!(vt.contents(), "  c\n   d\ne");
+        assert_eq!(vt.scrollback(), [" b", "a"]);
+    }
+
+    #[test]
+    fn test_erase() {
+        let full = "abcdef\r\nghijkl\r\nmnopqr\x1b[2;3H";
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[K")).contents(),
+            "abcdef\ngh\nmnopqr"
+        );
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[1K")).contents(),
+            "abcdef\n   jkl\nmnopqr"
+        );
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[2K")).contents(),
+            "abcdef\n\nmnopqr"
+        );
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[J")).contents(),
+            "abcdef\ngh"
+        );
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[1J")).contents(),
+            "\n   jkl\nmnopqr"
+        );
+        assert_eq!(render(6, 3, &format!("{full}\x1b[2J")).contents(), "");
+        assert_eq!(
+            render(6, 3, &format!("{full}\x1b[2P")).contents(),
+            "abcdef\nghkl\nmnopqr"
+        );
+        let vt = render(6, 3, &format!("{full}\x1bc"));
+        assert_eq!(vt.contents(), "");
+        assert_eq!(vt.cursor_position(), (0, 0));
+    }
+
+    #[test]
+    fn test_styles() {
+        let s = format!("a{}c", style("b").red().bold().force_styling(true));
+        let vt = render(10, 1, &s);
+        assert_eq!(vt.contents(), "abc");
+        assert_eq!(vt.cell(0, 0).unwrap().style(), &Style::new());
+        assert_eq!(vt.cell(1, 0).unwrap().style(), &Style::new().red().bold());
+        assert_eq!(vt.cell(2, 0).unwrap().style(), &Style::new());
+        assert_eq!(vt.cell(10, 0), None);
+    }
+
+    #[test]
+    fn test_cursor_visibility() {
+        let mut vt = render(10, 1, "\x1b[?25l");
+        assert!(!vt.is_cursor_visible());
+        vt.feed(b"\x1b[?25h");
+        assert!(vt.is_cursor_visible());
+    }
+
+    #[test]
+    fn test_split_input() {
+        let mut vt = VirtualTerminal::new(10, 2);
+        for chunk in [&b"ab\x1b["[..], b"2D\xe2", b"\x82\xac\x1b", b"[1mx"] {
+            io::Write::write_all(&mut vt, chunk).unwrap();
+        }
+        assert_eq!(vt.contents(), "€x");
+        assert_eq!(vt.cell(1, 0).unwrap().style(), &Style::new().bold());
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn test_wide_chars() {
+        let vt = render(5, 2, "日本語");
+        assert_eq!(vt.contents(), "日本\n語");
+        assert_eq!(vt.cell(1, 0).unwrap().text(), "");
+
+        let vt = render(5, 1, "日本\x1b[2Gx");
+        assert_eq!(vt.contents(), " x本");
+        let vt = render(5, 1, "e\u{301}x");
+        assert_eq!(vt.cell(0, 0).unwrap().text(), "e\u{301}");
+    }
+
+    #[test]
+    fn test_clear_last_lines() {
+        let screen = SharedTerminal(Arc::new(Mutex::new(VirtualTerminal::new(20, 5))));
+        let features = StreamFeatures::new().attended(true);
+        let term = Term::read_write_stream(io::empty(), screen.clone(), features);
+        term.write_line("first").unwrap();
+        term.write_line("second").unwrap();
+        term.write_line("third").unwrap();
+        term.clear_last_lines(2).unwrap();
+        term.write_line("fourth").unwrap();
+
+        let vt = screen.0.lock().unwrap();
+        assert_eq!(vt.contents(), "first\nfourth");
+        assert_eq!(vt.cursor_position(), (0, 2));
+    }
+}
@@ -1,3 +1,4 @@
+use core::time::Duration;
 use std::fmt::Display;
 use std::io;
 
@@ -52,9 +53,39 @@ pub(crate) fn read_single_key(_ctrlc_key: bool) -> io::Result<Key> {
     ))
 }
 
+pub(crate) fn read_single_key_timeout(
+    _ctrlc_key: bool,
+    _timeout: Duration,
+) -> io::Result<Option<Key>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "unsupported operation",
+    ))
+}
+
 #[inline]
 pub(crate) fn wants_emoji() -> bool {
     false
 }
 
+#[derive(Debug)]
+pub(crate) struct ResizeWatcher;
+
+impl ResizeWatcher {
+    pub(crate) fn new() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "unsupported operation",
+        ))
+    }
+
+    pub(crate) fn wait(&self, _timeout: i32) -> io::Result<bool> {
+        Ok(false)
+    }
+
+    pub(crate) fn drain(&self) -> bool {
+        false
+    }
+}
+
 pub(crate) fn set_title<T: Display>(_title: T) {}
@@ -1,6 +1,7 @@
 use core::fmt::Display;
 use core::iter::once;
 use core::mem::{self, MaybeUninit};
+use core::time::Duration;
 use core::{char, cmp};
 use std::env;
 use std::ffi::OsStr;
@@ -8,6 +9,8 @@ use std::io;
 use std::os::raw::c_void;
 use std::os::windows::ffi::OsStrExt;
 use std::os::windows::io::AsRawHandle;
+use std::thread;
+use std::time::Instant;
 
 use encode_unicode::error::Utf16TupleError;
 use encode_unicode::CharExt;
@@ -16,11 +19,11 @@ use windows_sys::Win32::Storage::FileSystem::{FileNameInfo, GetFileInformationBy
 use windows_sys::Win32::System::Console::CONSOLE_MODE;
 use windows_sys::Win32::System::Console::{
     FillConsoleOutputAttribute, FillConsoleOutputCharacterA, GetConsoleCursorInfo, GetConsoleMode,
-    GetConsoleScreenBufferInfo, GetNumberOfConsoleInputEvents, GetStdHandle, ReadConsoleInputW,
-    SetConsoleCursorInfo, SetConsoleCursorPosition, SetConsoleMode, SetConsoleTitleW,
-    CONSOLE_CURSOR_INFO, CONSOLE_SCREEN_BUFFER_INFO, COORD, ENABLE_PROCESSED_INPUT,
-    ENABLE_VIRTUAL_TERMINAL_PROCESSING, INPUT_RECORD, INPUT_RECORD_0, KEY_EVENT, KEY_EVENT_RECORD,
-    STD_ERROR_HANDLE, STD_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
+    GetConsoleScreenBufferInfo, GetNumberOfConsoleInputEvents, GetStdHandle, PeekConsoleInputW,
+    ReadConsoleInputW, SetConsoleCursorInfo, SetConsoleCursorPosition, SetConsoleMode,
+    SetConsoleTitleW, CONSOLE_CURSOR_INFO, CONSOLE_SCREEN_BUFFER_INFO, COORD,
+    ENABLE_PROCESSED_INPUT, ENABLE_VIRTUAL_TERMINAL_PROCESSING, INPUT_RECORD, INPUT_RECORD_0,
+    KEY_EVENT, KEY_EVENT_RECORD, STD_ERROR_HANDLE, STD_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
 };
 use windows_sys::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
 
@@ -40,6 +43,7 @@ pub(crate) fn is_a_terminal(out: &Term) -> bool {
     let (fd, others) = match out.target() {
         TermTarget::Stdout => (STD_OUTPUT_HANDLE, [STD_INPUT_HANDLE, STD_ERROR_HANDLE]),
         TermTarget::Stderr => (STD_ERROR_HANDLE, [STD_INPUT_HANDLE, STD_OUTPUT_HANDLE]),
+        TermTarget::ReadWriteStream(..) => return false,
     };
 
     if unsafe { console_on_any(&[fd]) } {
@@ -361,6 +365,49 @@ pub(crate) fn hide_cursor(out: &Term) -> io::Result<()> {
     Ok(())
 }
 
+pub(crate) fn enable_mouse_capture(out: &Term) -> io::Result<()> {
+    // the console reports the mouse as input records that are not read
+    if out.is_msys_tty {
+        return common_term::enable_mouse_capture(out);
+    }
+    Ok(())
+}
+
+pub(crate) fn disable_mouse_capture(out: &Term) -> io::Result<()> {
+    if out.is_msys_tty {
+        return common_term::disable_mouse_capture(out);
+    }
+    Ok(())
+}
+
+pub(crate) fn enable_bracketed_paste(out: &Term) -> io::Result<()> {
+    if out.is_msys_tty {
+        return common_term::enable_bracketed_paste(out);
+    }
+    Ok(())
+}
+
+pub(crate) fn disable_bracketed_paste(out: &Term) -> io::Result<()> {
+    if out.is_msys_tty {
+        return common_term::disable_bracketed_paste(out);
+    }
+    Ok(())
+}
+
+pub(crate) fn enable_focus_reporting(out: &Term) -> io::Result<()> {
+    if out.is_msys_tty {
+        return common_term::enable_focus_reporting(out);
+    }
+    Ok(())
+}
+
+pub(crate) fn disable_focus_reporting(out: &Term) -> io::Result<()> {
+    if out.is_msys_tty {
+        return common_term::disable_focus_reporting(out);
+    }
+    Ok(())
+}
+
 fn get_console_screen_buffer_info(hand: HANDLE) -> Option<(HANDLE, CONSOLE_SCREEN_BUFFER_INFO)> {
     let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = unsafe { mem::zeroed() };
     match unsafe { GetConsoleScreenBufferInfo(hand, &mut csbi) } {
@@ -394,6 +441,9 @@ pub(crate) fn key_from_key_code(code: VIRTUAL_KEY) -> Key {
         KeyboardAndMouse::VK_DELETE => Key::Del,
         KeyboardAndMouse::VK_SHIFT => Key::Shift,
         KeyboardAndMouse::VK_MENU => Key::Alt,
+        KeyboardAndMouse::VK_F1..=KeyboardAndMouse::VK_F24 => {
+            Key::F((code - KeyboardAndMouse::VK_F1 + 1) as u8)
+        }
         _ => Key::Unknown,
     }
 }
@@ -523,6 +573,50 @@ fn get_key_event_count() -> io::Result<u32> {
     }
 }
 
+/// Waits up to `timeout` for a key press and reads it.
+pub(crate) fn read_single_key_timeout(
+    ctrlc_key: bool,
+    timeout: Duration,
+) -> io::Result<Option<Key>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if key_down_pending()? {
+            return read_single_key(ctrlc_key).map(Some);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(10)));
+    }
+}
+
+/// Checks if a key press is waiting, discarding the input events before it.
+fn key_down_pending() -> io::Result<bool> {
+    let handle = get_stdin_handle()?;
+    while get_key_event_count()? > 0 {
+        let mut buffer: INPUT_RECORD = unsafe { mem::zeroed() };
+        let mut events_read: u32 = 0;
+        if unsafe { PeekConsoleInputW(handle, &mut buffer, 1, &mut events_read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if events_read == 0 {
+            break;
+        }
+        if buffer.EventType == KEY_EVENT as u16 {
+            let key_event =
+                unsafe { mem::transmute::<INPUT_RECORD_0, KEY_EVENT_RECORD>(buffer.Event) };
+            if key_event.bKeyDown != 0 {
+                return Ok(true);
+            }
+        }
+        if unsafe { ReadConsoleInputW(handle, &mut buffer, 1, &mut events_read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(false)
+}
+
 fn read_key_event() -> io::Result<KEY_EVENT_RECORD> {
     let handle = get_stdin_handle()?;
     let mut buffer: INPUT_RECORD = unsafe { mem::zeroed() };
@@ -623,6 +717,39 @@ pub(crate) fn msys_tty_on(term: &Term) -> bool {
     }
 }
 
+/// Watches for terminal resizes.
+///
+/// Console applications only receive resize events through the console
+/// input which belongs to whoever reads keys, so the size is polled.
+#[derive(Debug)]
+pub(crate) struct ResizeWatcher;
+
+impl ResizeWatcher {
+    const POLL_INTERVAL: i32 = 100;
+
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(ResizeWatcher)
+    }
+
+    /// Waits up to `timeout` milliseconds, -1 waits forever.
+    ///
+    /// Returns `true` if the caller should check the size again.
+    pub(crate) fn wait(&self, timeout: i32) -> io::Result<bool> {
+        let millis = if timeout < 0 {
+            Self::POLL_INTERVAL
+        } else {
+            timeout.min(Self::POLL_INTERVAL)
+        };
+        thread::sleep(Duration::from_millis(millis as u64));
+        Ok(true)
+    }
+
+    /// Returns whether a resize is pending, which polling never has.
+    pub(crate) fn drain(&self) -> bool {
+        false
+    }
+}
+
 pub(crate) fn set_title<T: Display>(title: T) {
     let buffer: Vec<u16> = OsStr::new(&format!("{title}"))
         .encode_wide()